@@ -0,0 +1,78 @@
+//! Parsing throughput on multi-megabyte replies, through the blocking
+//! parser, `FrameDecoder::decode_buf` and input arriving in small chunks.
+
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use redash_client::client::parser::{FrameDecoder, Parser};
+
+/// A `GET` of a large value.
+fn bulk_string(size: usize) -> Vec<u8> {
+    let mut reply = format!("${size}\r\n").into_bytes();
+    reply.resize(reply.len() + size, b'x');
+    reply.extend_from_slice(b"\r\n");
+    reply
+}
+
+/// An `LRANGE` of many short elements.
+fn array(items: usize, item_size: usize) -> Vec<u8> {
+    let item = "v".repeat(item_size);
+    let mut reply = format!("*{items}\r\n").into_bytes();
+    for _ in 0..items {
+        reply.extend_from_slice(format!("${item_size}\r\n{item}\r\n").as_bytes());
+    }
+    reply
+}
+
+/// An `HGETALL` as a RESP3 map of field names to integers.
+fn map(entries: usize) -> Vec<u8> {
+    let mut reply = format!("%{entries}\r\n").into_bytes();
+    for i in 0..entries {
+        reply.extend_from_slice(format!("+field:{i}\r\n:{i}\r\n").as_bytes());
+    }
+    reply
+}
+
+fn bench_replies(c: &mut Criterion) {
+    let replies = [
+        ("bulk_8mb", bulk_string(8 << 20)),
+        ("lrange_100k", array(100_000, 64)),
+        ("hgetall_100k", map(100_000)),
+    ];
+
+    let mut group = c.benchmark_group("parser");
+    group.sample_size(20);
+    for (name, reply) in &replies {
+        group.throughput(Throughput::Bytes(reply.len() as u64));
+
+        group.bench_function(format!("{name}/blocking"), |b| {
+            b.iter(|| Parser::new(&reply[..]).next().unwrap())
+        });
+
+        group.bench_function(format!("{name}/decode_buf"), |b| {
+            b.iter_batched(
+                || BytesMut::from(&reply[..]),
+                |mut buf| FrameDecoder::new().decode_buf(&mut buf).unwrap(),
+                BatchSize::LargeInput,
+            )
+        });
+
+        // the reply arriving in network sized reads
+        group.bench_function(format!("{name}/chunked"), |b| {
+            b.iter(|| {
+                let mut decoder = FrameDecoder::new();
+                let mut buf = BytesMut::new();
+                for chunk in reply.chunks(16 * 1024) {
+                    buf.extend_from_slice(chunk);
+                    if let Some(data) = decoder.decode_buf(&mut buf).unwrap() {
+                        return data;
+                    }
+                }
+                unreachable!("the reply is complete")
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_replies);
+criterion_main!(benches);
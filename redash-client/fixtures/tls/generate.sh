@@ -0,0 +1,20 @@
+#!/bin/sh
+# Regenerates the certificates used by the TLS tests.
+set -e
+cd "$(dirname "$0")"
+
+openssl req -x509 -newkey rsa:2048 -nodes -days 36500 -subj "/CN=redash test CA" \
+    -keyout ca.key -out ca.crt
+
+openssl req -newkey rsa:2048 -nodes -subj "/CN=localhost" \
+    -keyout server.key -out server.csr
+printf "subjectAltName=DNS:localhost,IP:127.0.0.1\n" > server.ext
+openssl x509 -req -in server.csr -CA ca.crt -CAkey ca.key -CAcreateserial -days 36500 \
+    -extfile server.ext -out server.crt
+
+openssl req -newkey rsa:2048 -nodes -subj "/CN=redash client" \
+    -keyout client.key -out client.csr
+openssl x509 -req -in client.csr -CA ca.crt -CAkey ca.key -CAcreateserial -days 36500 \
+    -out client.crt
+
+rm -f ./*.csr ./*.ext ./*.srl ca.key
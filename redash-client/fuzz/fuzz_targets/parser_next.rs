@@ -0,0 +1,53 @@
+//! Feeds arbitrary bytes to `Parser::next`, which must return errors on bad
+//! input and never panic, loop or allocate beyond the configured limits.
+//!
+//! ```text
+//! cargo +nightly fuzz run parser_next
+//! ```
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use redash_client::client::parser::{Decoded, FrameDecoder, Parser, ParserLimits};
+
+fuzz_target!(|data: &[u8]| {
+    let limits = ParserLimits {
+        max_bulk_len: 1 << 20,
+        max_aggregate_len: 1 << 16,
+        max_depth: 32,
+    };
+
+    // error replies are returned as errors too, keep reading after them
+    let parser = Parser::with_limits(data, limits);
+    let mut replies = Vec::new();
+    loop {
+        match parser.next() {
+            Ok(reply) => replies.push(Ok(reply)),
+            Err(err) if err.reply().is_some() => replies.push(Err(err.to_string())),
+            Err(_) => break,
+        }
+    }
+
+    // the same bytes arriving one at a time decode to the same replies
+    let mut decoder = FrameDecoder::with_limits(limits);
+    let mut buf = Vec::new();
+    let mut decoded = Vec::new();
+    for &byte in data {
+        buf.push(byte);
+        match decoder.decode(&buf) {
+            Ok(Decoded::Frame(reply, consumed)) => {
+                buf.drain(..consumed);
+                decoded.push(reply.into_result().map_err(|err| err.to_string()));
+            }
+            Ok(Decoded::Incomplete) => {}
+            Err(_) => break,
+        }
+    }
+    assert_eq!(replies.len(), decoded.len());
+    for (reply, decoded) in replies.iter().zip(&decoded) {
+        match (reply, decoded) {
+            (Ok(reply), Ok(decoded)) => assert_eq!(reply.to_string(), decoded.to_string()),
+            (reply, decoded) => assert_eq!(reply.is_ok(), decoded.is_ok()),
+        }
+    }
+});
@@ -1,54 +1,280 @@
-use std::{
-    cell::RefCell,
-    io::{self, Write},
-    net::TcpStream,
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    mpsc::Sender,
+    Mutex, MutexGuard,
 };
 
-use parser::{Data, Parser};
+use connection::{Connection, SessionState};
+use options::{Address, ConnectionOptions};
+use parser::Data;
+use pipeline::Pipeline;
+use reconnect::ReconnectEvent;
+use transport::Transport;
 
+#[cfg(feature = "tokio")]
+pub mod aio;
+pub mod cluster;
+pub mod command;
+pub mod commands;
+pub mod connection;
 pub mod errors;
+#[cfg(any(test, feature = "test-server"))]
+pub mod fake_server;
+pub mod from_data;
+pub mod options;
 pub mod parser;
+pub mod pipeline;
+pub mod pool;
+pub mod pubsub;
+pub mod reconnect;
+pub mod sentinel;
+pub mod serializer;
+pub mod transaction;
+pub mod transport;
 
-use errors::RedashError;
+use errors::{RedashError, ReplyError};
 
+/// RESP version negotiated with the server when connecting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProtocolVersion {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+/// Blocking client, `Send + Sync` so one configured client can be shared
+/// between threads, e.g. behind an `Arc`.
+///
+/// Commands from different threads are serialized on a single connection;
+/// each pipeline and transaction is written and read without interleaving.
+/// State spanning several calls is not isolated between threads, e.g. a
+/// `WATCH` or `SELECT` sent by one thread applies to the commands of all of
+/// them. Use [`Client::transaction_with_retry`] for check-and-set, or a
+/// [`pool::Pool`] to give each thread its own connection.
 pub struct Client {
-    url: String,
-    stream: RefCell<Option<TcpStream>>,
+    options: ConnectionOptions,
+    connection: Mutex<Option<Connection>>,
+    // set once `connect` succeeded, a missing connection is then reopened
+    connected: AtomicBool,
+    // database picked with SELECT, restored on reconnect
+    database: Mutex<Option<i64>>,
+    event_senders: Mutex<Vec<Sender<ReconnectEvent>>>,
+    server_address: Mutex<Option<Address>>,
 }
 
 impl Client {
     pub fn new(host: &str, port: u16) -> Self {
-        return Client {
-            url: format!("{host}:{}", port),
-            stream: RefCell::new(None),
-        };
+        Client::with_options(ConnectionOptions::new(host, port))
+    }
+
+    pub fn with_protocol(host: &str, port: u16, protocol: ProtocolVersion) -> Self {
+        Client::with_options(ConnectionOptions {
+            protocol,
+            ..ConnectionOptions::new(host, port)
+        })
+    }
+
+    /// Creates a client from a `redis://`, `rediss://` or `unix://` URL.
+    pub fn from_url(url: &str) -> Result<Self, RedashError> {
+        Ok(Client::with_options(ConnectionOptions::from_url(url)?))
+    }
+
+    pub fn with_options(options: ConnectionOptions) -> Self {
+        Client {
+            database: Mutex::new(options.database),
+            options,
+            connection: Mutex::new(None),
+            connected: AtomicBool::new(false),
+            event_senders: Mutex::new(Vec::new()),
+            server_address: Mutex::new(None),
+        }
+    }
+
+    pub fn options(&self) -> &ConnectionOptions {
+        &self.options
+    }
+
+    pub fn protocol(&self) -> ProtocolVersion {
+        self.options.protocol
     }
 
-    pub fn connect(&mut self) -> Result<(), io::Error> {
-        match TcpStream::connect(&self.url) {
-            Ok(stream) => {
-                self.stream = RefCell::new(Some(stream));
-                Ok(())
+    /// Opens the connection, replacing the current one if any.
+    pub fn connect(&self) -> Result<(), RedashError> {
+        let connection = self.open()?;
+        *self.lock_connection() = Some(connection);
+        self.connected.store(true, Ordering::SeqCst);
+        Ok(())
+    }
+
+    /// Opens a connection and runs the handshake for the current options.
+    fn open(&self) -> Result<Connection, RedashError> {
+        let (connection, address) = match &self.options.sentinel {
+            Some(sentinel) => {
+                sentinel::open_master(&self.options, sentinel, |address| self.open_at(address))?
             }
-            Err(e) => Err(e),
+            None => (
+                self.open_at(&self.options.address)?,
+                self.options.address.clone(),
+            ),
+        };
+        *lock(&self.server_address) = Some(address);
+        Ok(connection)
+    }
+
+    fn open_at(&self, address: &Address) -> Result<Connection, RedashError> {
+        let options = ConnectionOptions {
+            address: address.clone(),
+            database: *lock(&self.database),
+            ..self.options.clone()
+        };
+        let handshake = options.handshake()?;
+        let connection =
+            Connection::with_limits(Transport::connect(&options)?, options.parser_limits);
+        for command in handshake {
+            connection.request(&command)?;
         }
+        Ok(connection)
+    }
+
+    /// Address of the server last connected to, which is the master found
+    /// through Sentinel when configured.
+    pub fn server_address(&self) -> Option<Address> {
+        lock(&self.server_address).clone()
     }
 
+    /// Tokenizes a `redis-cli` style command line and sends it.
     pub fn send_command(&self, command: &str) -> Result<Data, RedashError> {
-        if self.stream.borrow().is_none() {
-            return Err(RedashError::OperationError(String::from("no_connection")));
-        }
-        if command.len() == 0 {
+        let args = command::tokenize(command)?;
+        self.send_args(&args)
+    }
+
+    /// Sends already split arguments, each one as a binary safe bulk string.
+    pub fn send_args<A: AsRef<[u8]>>(&self, args: &[A]) -> Result<Data, RedashError> {
+        if args.is_empty() {
             return Err(RedashError::OperationError(String::from("empty command")));
         }
-        let msg = format!("{command}\r\n");
-        let msg = msg.as_bytes();
-        let stream = self.stream.borrow_mut();
-        let mut stream = stream.as_ref().unwrap();
-        let reader = Parser::new(stream);
+        let reply = self.with_connection(|connection| connection.request(args))?;
 
-        stream.write(msg).unwrap();
+        if args[0].as_ref().eq_ignore_ascii_case(b"SELECT") {
+            let database = args
+                .get(1)
+                .and_then(|db| std::str::from_utf8(db.as_ref()).ok());
+            if let Some(database) = database.and_then(|db| db.parse().ok()) {
+                *lock(&self.database) = Some(database);
+            }
+        }
+        Ok(reply)
+    }
 
-        return reader.next();
+    /// Database picked with `SELECT`, or the configured one until then.
+    pub fn database(&self) -> Option<i64> {
+        *lock(&self.database)
+    }
+
+    /// Whether the connection is open, `false` once it broke until the next
+    /// command reconnects.
+    pub fn is_connected(&self) -> bool {
+        self.lock_connection().is_some()
+    }
+
+    /// State left on the server by earlier commands, e.g. an open `MULTI`.
+    pub fn session_state(&self) -> SessionState {
+        self.lock_connection()
+            .as_ref()
+            .map(Connection::state)
+            .unwrap_or_default()
+    }
+
+    /// Starts a pipeline of commands sent in a single round trip.
+    pub fn pipeline(&self) -> Pipeline<'_> {
+        Pipeline::new(self)
+    }
+
+    fn with_connection<R>(
+        &self,
+        f: impl FnOnce(&Connection) -> Result<R, RedashError>,
+    ) -> Result<R, RedashError> {
+        let mut guard = self.lock_connection();
+        if guard.is_none() {
+            if !self.connected.load(Ordering::SeqCst) {
+                return Err(RedashError::OperationError(String::from("no_connection")));
+            }
+            *guard = Some(self.reconnect()?);
+        }
+
+        let result = match guard.as_ref() {
+            Some(connection) => f(connection),
+            None => return Err(RedashError::OperationError(String::from("no_connection"))),
+        };
+        if let Err(err) = &result {
+            // after a failover the old master turns into a read only replica
+            let demoted =
+                self.options.sentinel.is_some() && err.reply().is_some_and(ReplyError::is_readonly);
+            if err.is_connection_error() || demoted {
+                // the stream is closed or out of sync, reconnect on the next command
+                *guard = None;
+                drop(guard);
+                self.emit(ReconnectEvent::Disconnected {
+                    reason: err.to_string(),
+                });
+            }
+        }
+        result
+    }
+
+    fn lock_connection(&self) -> MutexGuard<'_, Option<Connection>> {
+        self.connection.lock().unwrap_or_else(|poisoned| {
+            // a thread panicked mid request, the stream may be out of sync
+            self.connection.clear_poison();
+            let mut guard = poisoned.into_inner();
+            *guard = None;
+            guard
+        })
+    }
+}
+
+/// Locks state that stays consistent even if a holder panicked.
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fake_server::FakeServer;
+
+    #[test]
+    fn test_connect_reports_wrong_password() {
+        let server = FakeServer::start().unwrap();
+        server.require_password("secret");
+        let client = Client::with_options(ConnectionOptions {
+            password: Some(String::from("nope")),
+            ..server.options()
+        });
+
+        assert!(matches!(client.connect(), Err(RedashError::AuthFailed(_))));
+        assert!(matches!(
+            client.send_command("PING"),
+            Err(RedashError::OperationError(_))
+        ));
+    }
+
+    #[test]
+    fn test_client_is_shared_between_threads() {
+        let server = FakeServer::start().unwrap();
+        let client = std::sync::Arc::new(server.connect().unwrap());
+
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let client = client.clone();
+                std::thread::spawn(move || client.send_command("PING").unwrap())
+            })
+            .collect();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), Data::from("PONG"));
+        }
+        assert_eq!(server.commands(), vec!["PING"; 4]);
     }
 }
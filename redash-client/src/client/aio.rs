@@ -0,0 +1,343 @@
+//! Async client running on tokio, enabled with the `tokio` feature.
+//!
+//! One connection is shared by every clone of an [`AsyncClient`]: requests
+//! from all tasks are written in order by a background task, which hands
+//! each reply back to the task waiting for it.
+
+use std::{collections::VecDeque, io, time::Duration};
+
+use bytes::BytesMut;
+use futures_util::{SinkExt, StreamExt};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::TcpStream,
+    sync::{mpsc, oneshot},
+    time,
+};
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+use super::{
+    command,
+    errors::RedashError,
+    options::{Address, ConnectionOptions},
+    parser::{Data, FrameDecoder, ParserLimits},
+    transport,
+};
+
+/// Frames replies out of partially received buffers and encodes commands
+/// as RESP arrays.
+///
+/// Error replies are decoded as `Some(Err(_))` items so the stream of
+/// frames goes on; the codec only fails on data that cannot be parsed.
+#[derive(Debug, Default)]
+pub struct RespCodec {
+    decoder: FrameDecoder,
+}
+
+impl RespCodec {
+    pub fn with_limits(limits: ParserLimits) -> Self {
+        RespCodec {
+            decoder: FrameDecoder::with_limits(limits),
+        }
+    }
+}
+
+impl Decoder for RespCodec {
+    type Item = Result<Data, RedashError>;
+    type Error = RedashError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let frame = self.decoder.decode_buf(src)?;
+        Ok(frame.map(Data::into_result))
+    }
+}
+
+impl<A: AsRef<[u8]>> Encoder<&[A]> for RespCodec {
+    type Error = RedashError;
+
+    fn encode(&mut self, item: &[A], dst: &mut BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(&command::encode(item));
+        Ok(())
+    }
+}
+
+trait AsyncStream: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> AsyncStream for T {}
+
+type Responder = oneshot::Sender<Result<Data, RedashError>>;
+
+struct Request {
+    args: Vec<Vec<u8>>,
+    responder: Responder,
+}
+
+/// Async client multiplexing requests from many tasks over one connection.
+///
+/// Cloning is cheap and every clone shares the connection, which is closed
+/// once the last clone is dropped.
+#[derive(Clone)]
+pub struct AsyncClient {
+    requests: mpsc::UnboundedSender<Request>,
+    read_timeout: Option<Duration>,
+}
+
+impl AsyncClient {
+    /// Connects and runs the handshake described by the options.
+    pub async fn connect(options: &ConnectionOptions) -> Result<Self, RedashError> {
+        if options.sentinel.is_some() {
+            return Err(RedashError::OperationError(String::from(
+                "sentinel discovery is not supported by the async client",
+            )));
+        }
+        let handshake = options.handshake()?;
+        let stream = match options.connect_timeout {
+            Some(timeout) => time::timeout(timeout, connect_stream(options))
+                .await
+                .map_err(|_| timed_out("connect timed out"))??,
+            None => connect_stream(options).await?,
+        };
+
+        let (requests, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(run(
+            Framed::new(stream, RespCodec::with_limits(options.parser_limits)),
+            receiver,
+        ));
+
+        let client = AsyncClient {
+            requests,
+            read_timeout: options.read_timeout,
+        };
+        for command in handshake {
+            client.send_args(&command).await?;
+        }
+        Ok(client)
+    }
+
+    /// Tokenizes a `redis-cli` style command line and sends it.
+    pub async fn send_command(&self, command: &str) -> Result<Data, RedashError> {
+        let args = command::tokenize(command)?;
+        self.send_args(&args).await
+    }
+
+    /// Sends already split arguments and waits for the reply.
+    ///
+    /// Subscribe commands are refused: their confirmations and messages do
+    /// not answer one request each, which the shared connection relies on.
+    pub async fn send_args<A: AsRef<[u8]>>(&self, args: &[A]) -> Result<Data, RedashError> {
+        let name = match args.first() {
+            Some(name) => name.as_ref().to_ascii_uppercase(),
+            None => return Err(RedashError::OperationError(String::from("empty command"))),
+        };
+        if is_subscribe_command(&name) {
+            return Err(RedashError::OperationError(format!(
+                "{} is not supported by the async client",
+                String::from_utf8_lossy(&name)
+            )));
+        }
+        let (responder, reply) = oneshot::channel();
+        let request = Request {
+            args: args.iter().map(|arg| arg.as_ref().to_vec()).collect(),
+            responder,
+        };
+        if self.requests.send(request).is_err() {
+            return Err(connection_closed());
+        }
+
+        let reply = match self.read_timeout {
+            // the reply is dropped when it arrives late, the connection stays in sync
+            Some(timeout) => time::timeout(timeout, reply)
+                .await
+                .map_err(|_| timed_out("reply timed out"))?,
+            None => reply.await,
+        };
+        reply.unwrap_or_else(|_| Err(connection_closed()))
+    }
+}
+
+async fn connect_stream(options: &ConnectionOptions) -> Result<Box<dyn AsyncStream>, RedashError> {
+    match (&options.address, &options.tls) {
+        (Address::Tcp { host, port }, None) => {
+            let stream = TcpStream::connect((host.as_str(), *port)).await?;
+            Ok(Box::new(stream))
+        }
+        (Address::Tcp { host, port }, Some(tls)) => {
+            let stream = TcpStream::connect((host.as_str(), *port)).await?;
+            let connector = tokio_native_tls::TlsConnector::from(transport::tls_connector(tls)?);
+            let domain = tls.sni.as_deref().unwrap_or(host);
+            match connector.connect(domain, stream).await {
+                Ok(stream) => Ok(Box::new(stream)),
+                Err(err) => Err(RedashError::UnknownError(Box::new(err))),
+            }
+        }
+        (Address::Unix(_), Some(_)) => Err(RedashError::OperationError(String::from(
+            "TLS is not supported over unix sockets",
+        ))),
+        #[cfg(unix)]
+        (Address::Unix(path), None) => {
+            let stream = tokio::net::UnixStream::connect(path).await?;
+            Ok(Box::new(stream))
+        }
+        #[cfg(not(unix))]
+        (Address::Unix(_), None) => Err(RedashError::OperationError(String::from(
+            "unix sockets are not supported on this platform",
+        ))),
+    }
+}
+
+/// Writes queued requests and matches replies to them in order.
+async fn run(
+    mut framed: Framed<Box<dyn AsyncStream>, RespCodec>,
+    mut requests: mpsc::UnboundedReceiver<Request>,
+) {
+    let mut pending: VecDeque<Responder> = VecDeque::new();
+
+    let failure = loop {
+        tokio::select! {
+            request = requests.recv() => {
+                let mut request = match request {
+                    Some(request) => request,
+                    None => return,
+                };
+                // write everything queued so far with a single flush
+                loop {
+                    if let Err(err) = framed.feed(&request.args[..]).await {
+                        let _ = request.responder.send(Err(err));
+                        break;
+                    }
+                    pending.push_back(request.responder);
+                    match requests.try_recv() {
+                        Ok(next) => request = next,
+                        Err(_) => break,
+                    }
+                }
+                if let Err(err) = SinkExt::<&[Vec<u8>]>::flush(&mut framed).await {
+                    break err.to_string();
+                }
+            }
+            reply = framed.next() => match reply {
+                // out of band RESP3 pushes do not answer a request
+                Some(Ok(Ok(Data::Push(_)))) => (),
+                Some(Ok(reply)) => {
+                    if let Some(responder) = pending.pop_front() {
+                        let _ = responder.send(reply);
+                    }
+                }
+                Some(Err(err)) => break err.to_string(),
+                None => break String::from("connection closed"),
+            },
+        }
+    };
+
+    for responder in pending {
+        let _ = responder.send(Err(RedashError::IOError(io::Error::new(
+            io::ErrorKind::ConnectionAborted,
+            failure.clone(),
+        ))));
+    }
+}
+
+fn is_subscribe_command(name: &[u8]) -> bool {
+    matches!(
+        name,
+        b"SUBSCRIBE"
+            | b"UNSUBSCRIBE"
+            | b"PSUBSCRIBE"
+            | b"PUNSUBSCRIBE"
+            | b"SSUBSCRIBE"
+            | b"SUNSUBSCRIBE"
+    )
+}
+
+fn connection_closed() -> RedashError {
+    RedashError::IOError(io::Error::new(
+        io::ErrorKind::NotConnected,
+        "connection closed",
+    ))
+}
+
+fn timed_out(message: &str) -> RedashError {
+    RedashError::IOError(io::Error::new(io::ErrorKind::TimedOut, message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{fake_server::FakeServer, ProtocolVersion};
+
+    #[test]
+    fn test_codec_waits_for_complete_frames() {
+        let mut codec = RespCodec::default();
+        let mut buf = BytesMut::from(&b"*2\r\n$5\r\nhel"[..]);
+        assert!(codec.decode(&mut buf).unwrap().is_none());
+        assert_eq!(buf.len(), 11);
+
+        buf.extend_from_slice(b"lo\r\n-ERR no\r\n:1\r\n");
+        let reply = codec.decode(&mut buf).unwrap().unwrap().unwrap();
+        assert_eq!(
+            reply,
+            Data::Array(vec![
+                Data::from("hello"),
+                Data::Error(String::from("ERR no"))
+            ])
+        );
+        assert_eq!(&buf[..], b":1\r\n");
+
+        let reply = codec.decode(&mut buf).unwrap().unwrap().unwrap();
+        assert_eq!(reply, Data::Integer(1));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn test_codec_returns_error_replies_as_items() {
+        let mut codec = RespCodec::default();
+        let mut buf = BytesMut::from(&b"-ERR unknown command\r\n"[..]);
+        let reply = codec.decode(&mut buf).unwrap().unwrap();
+        assert_eq!(
+            reply.unwrap_err().reply().map(ToString::to_string),
+            Some(String::from("ERR unknown command"))
+        );
+        assert!(buf.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_requests_from_many_tasks_share_one_connection() {
+        let server = FakeServer::start().unwrap();
+        let client = AsyncClient::connect(&server.options()).await.unwrap();
+
+        let tasks: Vec<_> = (0..50)
+            .map(|i| {
+                let client = client.clone();
+                tokio::spawn(async move {
+                    let value = format!("value-{i}");
+                    let reply = client.send_args(&["ECHO", &value]).await.unwrap();
+                    assert_eq!(reply.as_str(), Some(value.as_str()));
+                })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_commands_are_refused() {
+        let server = FakeServer::start().unwrap();
+        let client = AsyncClient::connect(&ConnectionOptions {
+            protocol: ProtocolVersion::Resp3,
+            ..server.options()
+        })
+        .await
+        .unwrap();
+
+        let err = client.send_args(&["subscribe", "news"]).await.unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "SUBSCRIBE is not supported by the async client"
+        );
+        assert_eq!(
+            client.send_args(&["PING"]).await.unwrap(),
+            Data::from("PONG")
+        );
+        assert_eq!(server.commands()[1..], ["PING"]);
+    }
+}
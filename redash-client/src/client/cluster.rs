@@ -0,0 +1,947 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex, OnceLock, RwLock},
+    time::{Duration, Instant},
+};
+
+use super::{
+    errors::RedashError,
+    lock,
+    options::{Address, ConnectionOptions},
+    parser::Data,
+    Client,
+};
+
+/// Number of hash slots keys are spread over.
+pub const SLOT_COUNT: u16 = 16384;
+
+/// Redirects followed for one command before giving up.
+const MAX_REDIRECTS: usize = 5;
+
+/// Shortest time between two slot map reloads caused by redirects or
+/// failing nodes, so that a burst of them reloads the map once.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// CRC16/XMODEM, the checksum used to map keys to slots.
+fn crc16(bytes: &[u8]) -> u16 {
+    let mut crc: u16 = 0;
+    for byte in bytes {
+        crc ^= (*byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// Slot of a key. When the key contains a non-empty `{...}` hash tag only
+/// the tag is hashed, so `{user1}.name` and `{user1}.email` share a slot.
+pub fn key_slot(key: &[u8]) -> u16 {
+    let tag = key.iter().position(|b| *b == b'{').and_then(|open| {
+        let rest = &key[open + 1..];
+        match rest.iter().position(|b| *b == b'}') {
+            Some(0) | None => None,
+            Some(close) => Some(&rest[..close]),
+        }
+    });
+    crc16(tag.unwrap_or(key)) % SLOT_COUNT
+}
+
+/// Commands whose first argument is a key, grouped by data type.
+const FIRST_ARG_KEY: &[&str] = &[
+    // keys
+    "COPY DEL DUMP EXISTS EXPIRE EXPIREAT EXPIRETIME MOVE PERSIST PEXPIRE PEXPIREAT PEXPIRETIME",
+    "PTTL RENAME RENAMENX RESTORE SORT SORT_RO TOUCH TTL TYPE UNLINK WATCH",
+    // strings and bits
+    "APPEND BITCOUNT BITFIELD BITFIELD_RO BITPOS DECR DECRBY GET GETBIT GETDEL GETEX GETRANGE",
+    "GETSET INCR INCRBY INCRBYFLOAT LCS MGET MSET MSETNX PSETEX SET SETBIT SETEX SETNX SETRANGE",
+    "STRLEN SUBSTR",
+    // hashes
+    "HDEL HEXISTS HEXPIRE HGET HGETALL HINCRBY HINCRBYFLOAT HKEYS HLEN HMGET HMSET HPERSIST",
+    "HPEXPIRE HPTTL HRANDFIELD HSCAN HSET HSETNX HSTRLEN HTTL HVALS",
+    // lists
+    "BLMOVE BLPOP BRPOP BRPOPLPUSH LINDEX LINSERT LLEN LMOVE LPOP LPOS LPUSH LPUSHX LRANGE LREM",
+    "LSET LTRIM RPOP RPOPLPUSH RPUSH RPUSHX",
+    // sets
+    "SADD SCARD SDIFF SDIFFSTORE SINTER SINTERSTORE SISMEMBER SMEMBERS SMISMEMBER SMOVE SPOP",
+    "SRANDMEMBER SREM SSCAN SUNION SUNIONSTORE",
+    // sorted sets
+    "BZPOPMAX BZPOPMIN ZADD ZCARD ZCOUNT ZDIFFSTORE ZINCRBY ZINTERSTORE ZLEXCOUNT ZMSCORE ZPOPMAX",
+    "ZPOPMIN ZRANDMEMBER ZRANGE ZRANGEBYLEX ZRANGEBYSCORE ZRANGESTORE ZRANK ZREM ZREMRANGEBYLEX",
+    "ZREMRANGEBYRANK ZREMRANGEBYSCORE ZREVRANGE ZREVRANGEBYLEX ZREVRANGEBYSCORE ZREVRANK ZSCAN",
+    "ZSCORE ZUNIONSTORE",
+    // streams
+    "XACK XADD XAUTOCLAIM XCLAIM XDEL XLEN XPENDING XRANGE XREVRANGE XSETID XTRIM",
+    // hyperloglogs and geo
+    "PFADD PFCOUNT PFMERGE GEOADD GEODIST GEOHASH GEOPOS GEORADIUS GEORADIUSBYMEMBER",
+    "GEORADIUSBYMEMBER_RO GEORADIUS_RO GEOSEARCH GEOSEARCHSTORE",
+    // sharded channels hash like keys
+    "SPUBLISH SSUBSCRIBE SUNSUBSCRIBE",
+];
+
+/// Index of the argument a command is routed by, `None` when it has no key
+/// and can run on any node.
+///
+/// Commands missing from the table are sent to any node as well; should
+/// they have a key the node answers with a `MOVED` redirect to follow.
+fn key_index<A: AsRef<[u8]>>(args: &[A]) -> Option<usize> {
+    let name = args.first()?.as_ref().to_ascii_uppercase();
+    // the first key follows a count of keys at `index`, none when it is 0
+    let numkeys = |index: usize| {
+        integer_arg(args.get(index)?.as_ref())
+            .filter(|numkeys| *numkeys > 0)
+            .map(|_| index + 1)
+    };
+    // the first key follows a keyword, searched for from `from` on
+    let after = |from: usize, keyword: &[u8]| {
+        args.iter()
+            .skip(from)
+            .position(|arg| arg.as_ref().eq_ignore_ascii_case(keyword))
+            .map(|position| from + position + 1)
+    };
+
+    match &name[..] {
+        b"EVAL" | b"EVALSHA" | b"EVAL_RO" | b"EVALSHA_RO" | b"FCALL" | b"FCALL_RO" => numkeys(2),
+        b"LMPOP" | b"SINTERCARD" | b"ZDIFF" | b"ZINTER" | b"ZINTERCARD" | b"ZMPOP" | b"ZUNION" => {
+            numkeys(1)
+        }
+        b"BLMPOP" | b"BZMPOP" => numkeys(2),
+        b"XREAD" | b"XREADGROUP" => after(1, b"STREAMS"),
+        // MIGRATE host port key|"" destination-db timeout [...] [KEYS key...]
+        b"MIGRATE" => match args.get(3) {
+            Some(key) if !key.as_ref().is_empty() => Some(3),
+            _ => after(6, b"KEYS"),
+        },
+        // BITOP operation destkey key..., and subcommands taking a key
+        b"BITOP" | b"OBJECT" | b"MEMORY" | b"XGROUP" | b"XINFO" => Some(2),
+        name if first_arg_key_commands().contains(name) => Some(1),
+        _ => None,
+    }
+    .filter(|index| *index < args.len())
+}
+
+/// [`FIRST_ARG_KEY`] split into single command names, once.
+fn first_arg_key_commands() -> &'static HashSet<&'static [u8]> {
+    static COMMANDS: OnceLock<HashSet<&'static [u8]>> = OnceLock::new();
+    COMMANDS.get_or_init(|| {
+        FIRST_ARG_KEY
+            .iter()
+            .flat_map(|group| group.split_ascii_whitespace())
+            .map(str::as_bytes)
+            .collect()
+    })
+}
+
+fn integer_arg(arg: &[u8]) -> Option<i64> {
+    std::str::from_utf8(arg).ok()?.parse().ok()
+}
+
+/// A redirect replied by a node that does not serve the slot of a key.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Redirect {
+    /// The slot belongs to another node, the slot map is out of date.
+    Moved { slot: u16, address: Address },
+    /// The slot is being migrated; only the next command goes to the
+    /// target node, preceded by `ASKING`.
+    Ask { slot: u16, address: Address },
+}
+
+impl Redirect {
+    /// Reads a `MOVED` or `ASK` error reply. A target without a host
+    /// lives on the same host as the node that replied.
+    pub fn from_error(err: &RedashError, from: &Address) -> Option<Redirect> {
+        let reply = err.reply()?;
+        let target = reply.redirect()?;
+        let address = node_address(&target.host, target.port, from)?;
+        if reply.is_moved() {
+            Some(Redirect::Moved {
+                slot: target.slot,
+                address,
+            })
+        } else {
+            Some(Redirect::Ask {
+                slot: target.slot,
+                address,
+            })
+        }
+    }
+}
+
+fn node_address(host: &str, port: u16, from: &Address) -> Option<Address> {
+    match (host, from) {
+        ("?", _) => None,
+        ("", Address::Tcp { host, .. }) => Some(Address::tcp(host, port)),
+        ("", Address::Unix(_)) => None,
+        (host, _) => Some(Address::tcp(host, port)),
+    }
+}
+
+/// A range of slots and the master node serving it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlotRange {
+    pub start: u16,
+    pub end: u16,
+    pub master: Address,
+}
+
+/// Client for a Redis Cluster, routing every command to the master serving
+/// the slot of its key.
+///
+/// The slot map is loaded from the seed nodes on [`ClusterClient::connect`]
+/// and refreshed when a node answers with a `MOVED` redirect or cannot be
+/// reached, e.g. after a failover.
+pub struct ClusterClient {
+    options: ConnectionOptions,
+    seeds: Vec<Address>,
+    slots: RwLock<Vec<SlotRange>>,
+    nodes: Mutex<HashMap<Address, Arc<Client>>>,
+    // last reload caused by a redirect or a failing node
+    last_refresh: Mutex<Option<Instant>>,
+}
+
+impl ClusterClient {
+    /// Creates a client using the address of the options as the only seed.
+    pub fn with_options(options: ConnectionOptions) -> Self {
+        let seeds = vec![options.address.clone()];
+        ClusterClient::with_seeds(options, seeds)
+    }
+
+    /// Creates a client discovering the cluster through the given nodes;
+    /// every other option applies to all node connections.
+    pub fn with_seeds(options: ConnectionOptions, seeds: Vec<Address>) -> Self {
+        ClusterClient {
+            options,
+            seeds,
+            slots: RwLock::new(Vec::new()),
+            nodes: Mutex::new(HashMap::new()),
+            last_refresh: Mutex::new(None),
+        }
+    }
+
+    /// Loads the slot map from the first seed that answers.
+    pub fn connect(&self) -> Result<(), RedashError> {
+        self.refresh_slots()
+    }
+
+    /// Slot ranges and their masters as last loaded, sorted by slot.
+    pub fn slot_ranges(&self) -> Vec<SlotRange> {
+        self.slots
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clone()
+    }
+
+    /// Master currently believed to serve a slot.
+    pub fn slot_owner(&self, slot: u16) -> Option<Address> {
+        let slots = self
+            .slots
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        find_range(&slots, slot).map(|index| slots[index].master.clone())
+    }
+
+    /// Reloads the slot map with `CLUSTER SHARDS`, or `CLUSTER SLOTS` on
+    /// servers older than 7.0, asking known masters before the seeds.
+    pub fn refresh_slots(&self) -> Result<(), RedashError> {
+        let mut candidates: Vec<Address> = Vec::new();
+        for address in self
+            .slot_ranges()
+            .into_iter()
+            .map(|range| range.master)
+            .chain(self.seeds.iter().cloned())
+        {
+            if !candidates.contains(&address) {
+                candidates.push(address);
+            }
+        }
+
+        let mut last_error =
+            RedashError::OperationError(String::from("no cluster node to load slots from"));
+        for address in candidates {
+            match self.load_slots(&address) {
+                Ok(ranges) => {
+                    *self
+                        .slots
+                        .write()
+                        .unwrap_or_else(|poisoned| poisoned.into_inner()) = ranges;
+                    return Ok(());
+                }
+                Err(err) => last_error = err,
+            }
+        }
+        Err(last_error)
+    }
+
+    fn load_slots(&self, address: &Address) -> Result<Vec<SlotRange>, RedashError> {
+        let node = self.node(address)?;
+        let mut ranges = match node.send_args(&["CLUSTER", "SHARDS"]) {
+            Ok(reply) => parse_shards(reply, address)?,
+            // unknown subcommand before Redis 7.0
+            Err(err) if err.reply().is_some() => {
+                parse_slots(node.send_args(&["CLUSTER", "SLOTS"])?, address)?
+            }
+            Err(err) => return Err(err),
+        };
+        if ranges.is_empty() {
+            return Err(RedashError::OperationError(format!(
+                "{address} returned an empty slot map"
+            )));
+        }
+        ranges.sort_by_key(|range| range.start);
+        Ok(ranges)
+    }
+
+    /// Tokenizes a `redis-cli` style command line and sends it.
+    pub fn send_command(&self, command: &str) -> Result<Data, RedashError> {
+        let args = super::command::tokenize(command)?;
+        self.send_args(&args)
+    }
+
+    /// Sends a command to the node serving its key, following redirects.
+    pub fn send_args<A: AsRef<[u8]>>(&self, args: &[A]) -> Result<Data, RedashError> {
+        if args.is_empty() {
+            return Err(RedashError::OperationError(String::from("empty command")));
+        }
+        let slot = key_index(args).map(|index| key_slot(args[index].as_ref()));
+        let owner = || {
+            slot.and_then(|slot| self.slot_owner(slot))
+                .or_else(|| self.any_node())
+                .ok_or_else(|| RedashError::OperationError(String::from("no_connection")))
+        };
+        let mut address = owner()?;
+
+        let mut asking = false;
+        let mut reconnected = false;
+        for _ in 0..=MAX_REDIRECTS {
+            let node = match self.node(&address) {
+                Ok(node) => node,
+                // nothing was sent yet, try the node now serving the slot
+                Err(err) if err.is_connection_error() && !reconnected => {
+                    self.node_failed(&address);
+                    reconnected = true;
+                    asking = false;
+                    address = owner()?;
+                    continue;
+                }
+                Err(err) => return Err(err),
+            };
+            let reply = if asking {
+                let mut pipeline = node.pipeline();
+                pipeline.args(&["ASKING"]).args(args);
+                pipeline.execute()?.pop().unwrap_or_else(|| {
+                    Err(RedashError::OperationError(String::from(
+                        "missing reply after ASKING",
+                    )))
+                })
+            } else {
+                node.send_args(args)
+            };
+
+            let err = match reply {
+                Err(err) => err,
+                reply => return reply,
+            };
+            match Redirect::from_error(&err, &address) {
+                Some(Redirect::Moved {
+                    slot,
+                    address: target,
+                }) => {
+                    self.assign(slot, target.clone());
+                    // a single moved slot usually means a resharding or a failover
+                    self.refresh_topology();
+                    asking = false;
+                    address = target;
+                }
+                Some(Redirect::Ask {
+                    address: target, ..
+                }) => {
+                    asking = true;
+                    address = target;
+                }
+                None => {
+                    // the command may have run, it is not sent again
+                    if err.is_connection_error() {
+                        self.node_failed(&address);
+                    }
+                    return Err(err);
+                }
+            }
+        }
+        Err(RedashError::OperationError(String::from(
+            "too many cluster redirects",
+        )))
+    }
+
+    /// Reloads the slot map unless a redirect or a failing node already
+    /// did within [`MIN_REFRESH_INTERVAL`].
+    fn refresh_topology(&self) {
+        {
+            let mut last_refresh = lock(&self.last_refresh);
+            if last_refresh.is_some_and(|last| last.elapsed() < MIN_REFRESH_INTERVAL) {
+                return;
+            }
+            *last_refresh = Some(Instant::now());
+        }
+        let _ = self.refresh_slots();
+    }
+
+    /// Drops the connection to a node that cannot be reached and reloads
+    /// the slot map, its slots may have moved to a promoted replica.
+    fn node_failed(&self, address: &Address) {
+        lock(&self.nodes).remove(address);
+        self.refresh_topology();
+    }
+
+    fn any_node(&self) -> Option<Address> {
+        let slots = self
+            .slots
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        slots
+            .first()
+            .map(|range| range.master.clone())
+            .or_else(|| self.seeds.first().cloned())
+    }
+
+    /// Moves a single slot to another master until the next refresh.
+    fn assign(&self, slot: u16, master: Address) {
+        let mut slots = self
+            .slots
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut moved = vec![SlotRange {
+            start: slot,
+            end: slot,
+            master,
+        }];
+        let index = match find_range(&slots, slot) {
+            Some(index) => {
+                let range = slots.remove(index);
+                if range.start < slot {
+                    moved.insert(
+                        0,
+                        SlotRange {
+                            end: slot - 1,
+                            ..range.clone()
+                        },
+                    );
+                }
+                if slot < range.end {
+                    moved.push(SlotRange {
+                        start: slot + 1,
+                        ..range
+                    });
+                }
+                index
+            }
+            None => slots.partition_point(|range| range.start < slot),
+        };
+        slots.splice(index..index, moved);
+    }
+
+    /// Connection to a node, opened on first use.
+    fn node(&self, address: &Address) -> Result<Arc<Client>, RedashError> {
+        if let Some(node) = lock(&self.nodes).get(address) {
+            return Ok(node.clone());
+        }
+
+        let node = Client::with_options(ConnectionOptions {
+            address: address.clone(),
+            ..self.options.clone()
+        });
+        node.connect()?;
+        Ok(lock(&self.nodes)
+            .entry(address.clone())
+            .or_insert_with(|| Arc::new(node))
+            .clone())
+    }
+}
+
+fn find_range(slots: &[SlotRange], slot: u16) -> Option<usize> {
+    let index = slots.partition_point(|range| range.end < slot);
+    slots
+        .get(index)
+        .filter(|range| range.start <= slot)
+        .map(|_| index)
+}
+
+fn integer(data: &Data) -> Option<i64> {
+    match data {
+        Data::Integer(value) => Some(*value),
+        data => data.as_str()?.parse().ok(),
+    }
+}
+
+/// Fields of a map reply, sent as a flat array of pairs over RESP2.
+fn fields(data: Data) -> Vec<(Vec<u8>, Data)> {
+    let pairs: Vec<(Data, Data)> = match data {
+        Data::Map(pairs) => pairs,
+        Data::Array(items) => {
+            let mut items = items.into_iter();
+            let mut pairs = Vec::new();
+            while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                pairs.push((key, value));
+            }
+            pairs
+        }
+        _ => Vec::new(),
+    };
+    pairs
+        .into_iter()
+        .filter_map(|(key, value)| Some((key.into_bytes()?, value)))
+        .collect()
+}
+
+/// Parses a `CLUSTER SLOTS` reply: `[start, end, [host, port, id], replicas...]`.
+fn parse_slots(reply: Data, from: &Address) -> Result<Vec<SlotRange>, RedashError> {
+    let entries = match reply {
+        Data::Array(entries) => entries,
+        _ => return Ok(Vec::new()),
+    };
+
+    let mut ranges = Vec::new();
+    for entry in entries {
+        let items = match entry {
+            Data::Array(items) if items.len() >= 3 => items,
+            _ => continue,
+        };
+        let (Some(start), Some(end)) = (integer(&items[0]), integer(&items[1])) else {
+            continue;
+        };
+        let master = match &items[2] {
+            Data::Array(node) if node.len() >= 2 => match (node[0].as_str(), integer(&node[1])) {
+                (Some(host), Some(port)) => node_address(host, port_number(port)?, from),
+                _ => None,
+            },
+            _ => None,
+        };
+        if let Some(master) = master {
+            ranges.push(slot_range(start, end, master)?);
+        }
+    }
+    Ok(ranges)
+}
+
+/// Parses a `CLUSTER SHARDS` reply, a list of shards with their slot
+/// ranges and nodes.
+fn parse_shards(reply: Data, from: &Address) -> Result<Vec<SlotRange>, RedashError> {
+    let shards = match reply {
+        Data::Array(shards) => shards,
+        _ => return Ok(Vec::new()),
+    };
+
+    let mut ranges = Vec::new();
+    for shard in shards {
+        let mut slots = Vec::new();
+        let mut master = None;
+        for (key, value) in fields(shard) {
+            match (&key[..], value) {
+                (b"slots", Data::Array(bounds)) => {
+                    slots = bounds.iter().filter_map(integer).collect();
+                }
+                (b"nodes", Data::Array(nodes)) => {
+                    master = nodes
+                        .into_iter()
+                        .map(fields)
+                        .find(|node| {
+                            node.iter().any(|(key, value)| {
+                                key == b"role" && value.as_str() == Some("master")
+                            })
+                        })
+                        .map(|node| shard_node_address(node, from))
+                        .transpose()?
+                        .flatten();
+                }
+                _ => (),
+            }
+        }
+
+        if let Some(master) = master {
+            for bounds in slots.chunks_exact(2) {
+                ranges.push(slot_range(bounds[0], bounds[1], master.clone())?);
+            }
+        }
+    }
+    Ok(ranges)
+}
+
+fn shard_node_address(
+    node: Vec<(Vec<u8>, Data)>,
+    from: &Address,
+) -> Result<Option<Address>, RedashError> {
+    let field = |name: &[u8]| {
+        node.iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    };
+    let Some(port) = field(b"port").and_then(integer) else {
+        return Ok(None);
+    };
+    let port = port_number(port)?;
+    let endpoint = field(b"endpoint").and_then(Data::as_str);
+    let ip = field(b"ip").and_then(Data::as_str);
+    Ok(
+        match endpoint.filter(|endpoint| !endpoint.is_empty() && *endpoint != "?") {
+            Some(endpoint) => node_address(endpoint, port, from),
+            None => ip.and_then(|ip| node_address(ip, port, from)),
+        },
+    )
+}
+
+fn slot_range(start: i64, end: i64, master: Address) -> Result<SlotRange, RedashError> {
+    let slot = |value: i64| u16::try_from(value).ok().filter(|slot| *slot < SLOT_COUNT);
+    match (slot(start), slot(end)) {
+        (Some(start), Some(end)) if start <= end => Ok(SlotRange { start, end, master }),
+        _ => Err(invalid_slot_map(&format!(
+            "invalid slot range {start}-{end}"
+        ))),
+    }
+}
+
+fn port_number(port: i64) -> Result<u16, RedashError> {
+    u16::try_from(port).map_err(|_| invalid_slot_map(&format!("invalid port {port}")))
+}
+
+fn invalid_slot_map(reason: &str) -> RedashError {
+    RedashError::OperationError(format!("invalid cluster slot map: {reason}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicU16, Ordering};
+
+    use super::*;
+    use crate::client::fake_server::{FakeServer, Scripted};
+
+    #[test]
+    fn test_key_slot() {
+        assert_eq!(crc16(b"123456789"), 0x31C3);
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+        assert_eq!(key_slot(b"{user1000}.followers"), key_slot(b"user1000"));
+        // an empty tag hashes the whole key, the first closing brace ends a tag
+        assert_eq!(key_slot(b"foo{}{bar}"), crc16(b"foo{}{bar}") % SLOT_COUNT);
+        assert_eq!(key_slot(b"foo{{bar}}zap"), key_slot(b"{bar"));
+    }
+
+    #[test]
+    fn test_key_index() {
+        assert_eq!(key_index(&["GET", "a"]), Some(1));
+        assert_eq!(key_index(&["PING"]), None);
+        assert_eq!(key_index(&["EVAL", "return 1", "0"]), None);
+        assert_eq!(key_index(&["EVAL", "return 1", "1", "k"]), Some(3));
+        assert_eq!(
+            key_index(&["XREAD", "COUNT", "2", "STREAMS", "s", "0"]),
+            Some(4)
+        );
+        assert_eq!(key_index(&["FCALL", "f", "2", "a", "b"]), Some(3));
+        assert_eq!(key_index(&["ZUNION", "2", "a", "b"]), Some(2));
+        assert_eq!(key_index(&["BITOP", "AND", "dest", "a", "b"]), Some(2));
+        assert_eq!(key_index(&["OBJECT", "ENCODING", "a"]), Some(2));
+        assert_eq!(key_index(&["MEMORY", "USAGE", "a"]), Some(2));
+        assert_eq!(
+            key_index(&["MIGRATE", "10.0.0.2", "6379", "a", "0", "5000"]),
+            Some(3)
+        );
+        assert_eq!(
+            key_index(&["MIGRATE", "10.0.0.2", "6379", "", "0", "5000", "KEYS", "a", "b"]),
+            Some(7)
+        );
+        // keyless commands go to any node rather than by their first argument
+        assert_eq!(key_index(&["INFO", "server"]), None);
+        assert_eq!(key_index(&["CONFIG", "GET", "maxmemory"]), None);
+        assert_eq!(key_index(&["MEMORY", "STATS"]), None);
+        assert_eq!(key_index(&["get", "a"]), Some(1));
+    }
+
+    #[test]
+    fn test_parse_slot_maps() {
+        let from = Address::tcp("10.0.0.1", 7000);
+        let slots = Data::Array(vec![
+            Data::Array(vec![
+                Data::Integer(0),
+                Data::Integer(5460),
+                Data::Array(vec![
+                    Data::from("10.0.0.1"),
+                    Data::Integer(7000),
+                    Data::from("id1"),
+                ]),
+                Data::Array(vec![
+                    Data::from("10.0.0.4"),
+                    Data::Integer(7003),
+                    Data::from("id4"),
+                ]),
+            ]),
+            Data::Array(vec![
+                Data::Integer(5461),
+                Data::Integer(16383),
+                Data::Array(vec![Data::from(""), Data::Integer(7001)]),
+            ]),
+        ]);
+        assert_eq!(
+            parse_slots(slots, &from).unwrap(),
+            vec![
+                SlotRange {
+                    start: 0,
+                    end: 5460,
+                    master: Address::tcp("10.0.0.1", 7000)
+                },
+                SlotRange {
+                    start: 5461,
+                    end: 16383,
+                    master: Address::tcp("10.0.0.1", 7001)
+                },
+            ]
+        );
+
+        let node = |ip: &str, port, role: &str| {
+            Data::Array(vec![
+                Data::from("ip"),
+                Data::from(ip),
+                Data::from("endpoint"),
+                Data::from(ip),
+                Data::from("port"),
+                Data::Integer(port),
+                Data::from("role"),
+                Data::from(role),
+            ])
+        };
+        let shards = Data::Array(vec![Data::Array(vec![
+            Data::from("slots"),
+            Data::Array(vec![
+                Data::Integer(0),
+                Data::Integer(100),
+                Data::Integer(200),
+                Data::Integer(300),
+            ]),
+            Data::from("nodes"),
+            Data::Array(vec![
+                node("10.0.0.4", 7003, "replica"),
+                node("10.0.0.1", 7000, "master"),
+            ]),
+        ])]);
+        let ranges = parse_shards(shards, &from).unwrap();
+        assert_eq!(ranges.len(), 2);
+        assert_eq!((ranges[1].start, ranges[1].end), (200, 300));
+        assert_eq!(ranges[1].master, Address::tcp("10.0.0.1", 7000));
+    }
+
+    #[test]
+    fn test_parse_slot_maps_out_of_range() {
+        let from = Address::tcp("10.0.0.1", 7000);
+        let slots = |end, port| {
+            Data::Array(vec![Data::Array(vec![
+                Data::Integer(0),
+                Data::Integer(end),
+                Data::Array(vec![Data::from("10.0.0.1"), Data::Integer(port)]),
+            ])])
+        };
+        assert!(parse_slots(slots(16383, 7000), &from).is_ok());
+        assert!(matches!(
+            parse_slots(slots(16384, 7000), &from),
+            Err(RedashError::OperationError(_))
+        ));
+        assert!(matches!(
+            parse_slots(slots(-1, 7000), &from),
+            Err(RedashError::OperationError(_))
+        ));
+        assert!(matches!(
+            parse_slots(slots(16383, 65536), &from),
+            Err(RedashError::OperationError(_))
+        ));
+
+        let shards = Data::Array(vec![Data::Array(vec![
+            Data::from("slots"),
+            Data::Array(vec![Data::Integer(0), Data::Integer(20000)]),
+            Data::from("nodes"),
+            Data::Array(vec![Data::Array(vec![
+                Data::from("ip"),
+                Data::from("10.0.0.1"),
+                Data::from("port"),
+                Data::Integer(7000),
+                Data::from("role"),
+                Data::from("master"),
+            ])]),
+        ])]);
+        assert!(parse_shards(shards, &from).is_err());
+    }
+
+    #[test]
+    fn test_parse_redirects() {
+        let from = Address::tcp("10.0.0.1", 7000);
+        let moved = RedashError::from_reply("MOVED 3999 10.0.0.2:6379");
+        assert_eq!(
+            Redirect::from_error(&moved, &from),
+            Some(Redirect::Moved {
+                slot: 3999,
+                address: Address::tcp("10.0.0.2", 6379)
+            })
+        );
+        let ask = RedashError::from_reply("ASK 3999 :6380");
+        assert_eq!(
+            Redirect::from_error(&ask, &from),
+            Some(Redirect::Ask {
+                slot: 3999,
+                address: Address::tcp("10.0.0.1", 6380)
+            })
+        );
+        let other = RedashError::from_reply("ERR unknown command");
+        assert_eq!(Redirect::from_error(&other, &from), None);
+    }
+
+    /// A node answering `CLUSTER SLOTS` with every slot served by the node
+    /// on port `owner`, and `GET` with `get`.
+    fn node(owner: Arc<AtomicU16>, get: fn(u16) -> Data) -> FakeServer {
+        let server = FakeServer::start().unwrap();
+        let slots_owner = owner.clone();
+        server.handle("CLUSTER", move |args| {
+            Scripted::Reply(match &args[1].to_ascii_uppercase()[..] {
+                b"SLOTS" => Data::Array(vec![Data::Array(vec![
+                    Data::Integer(0),
+                    Data::Integer(16383),
+                    Data::Array(vec![
+                        Data::from("127.0.0.1"),
+                        Data::Integer(slots_owner.load(Ordering::SeqCst).into()),
+                    ]),
+                ])]),
+                _ => Data::Error(String::from("ERR unknown subcommand 'SHARDS'")),
+            })
+        });
+        server.handle("GET", move |_| {
+            Scripted::Reply(get(owner.load(Ordering::SeqCst)))
+        });
+        server
+    }
+
+    #[test]
+    fn test_moved_redirect_refreshes_slots() {
+        let owner = Arc::new(AtomicU16::new(0));
+        let target = node(owner.clone(), |_| Data::from("bar"));
+        let source = node(owner.clone(), |owner| {
+            Data::Error(format!("MOVED 12182 127.0.0.1:{owner}"))
+        });
+        owner.store(source.port().unwrap(), Ordering::SeqCst);
+
+        let cluster = ClusterClient::with_options(source.options());
+        cluster.connect().unwrap();
+        assert_eq!(
+            cluster.slot_owner(key_slot(b"foo")),
+            Some(source.address().clone())
+        );
+
+        // the slot migrates to the other node
+        owner.store(target.port().unwrap(), Ordering::SeqCst);
+        assert_eq!(cluster.send_command("GET foo").unwrap(), Data::from("bar"));
+        assert_eq!(
+            cluster.slot_ranges(),
+            vec![SlotRange {
+                start: 0,
+                end: 16383,
+                master: target.address().clone()
+            }]
+        );
+    }
+
+    #[test]
+    fn test_ask_redirect_is_followed_once() {
+        let owner = Arc::new(AtomicU16::new(0));
+        let importing = FakeServer::start().unwrap();
+        importing.handle("ASKING", |_| Scripted::Reply(Data::from("OK")));
+        importing.handle("GET", |_| Scripted::Reply(Data::from("bar")));
+        let source = node(owner.clone(), |importing| {
+            Data::Error(format!("ASK 12182 127.0.0.1:{importing}"))
+        });
+
+        let cluster = ClusterClient::with_options(source.options());
+        // the map is loaded from the source while it still owns every slot
+        owner.store(source.port().unwrap(), Ordering::SeqCst);
+        cluster.connect().unwrap();
+        owner.store(importing.port().unwrap(), Ordering::SeqCst);
+
+        assert_eq!(cluster.send_command("GET foo").unwrap(), Data::from("bar"));
+        assert_eq!(
+            cluster.slot_owner(key_slot(b"foo")),
+            Some(source.address().clone())
+        );
+        assert!(importing
+            .commands()
+            .ends_with(&["ASKING", "GET foo"].map(String::from)));
+    }
+
+    #[test]
+    fn test_redirect_burst_refreshes_slots_once() {
+        let owner = Arc::new(AtomicU16::new(0));
+        let target = node(owner.clone(), |_| Data::from("bar"));
+        // a stale node claiming every slot while redirecting every key
+        let source = node(owner.clone(), |_| Data::Null);
+        let target_port = target.port().unwrap();
+        source.handle("GET", move |args| {
+            Scripted::Reply(Data::Error(format!(
+                "MOVED {} 127.0.0.1:{target_port}",
+                key_slot(&args[1])
+            )))
+        });
+        owner.store(source.port().unwrap(), Ordering::SeqCst);
+
+        let cluster = ClusterClient::with_options(source.options());
+        cluster.connect().unwrap();
+        assert_eq!(cluster.send_command("GET foo").unwrap(), Data::from("bar"));
+        assert_eq!(cluster.send_command("GET bar").unwrap(), Data::from("bar"));
+
+        // once on connect, once for the burst
+        let refreshes = source
+            .commands()
+            .iter()
+            .filter(|command| *command == "CLUSTER SLOTS")
+            .count();
+        assert_eq!(refreshes, 2);
+    }
+
+    #[test]
+    fn test_failed_node_refreshes_slots() {
+        let owner = Arc::new(AtomicU16::new(0));
+        let source = node(owner.clone(), |_| Data::from("old"));
+        let target = node(owner.clone(), |_| Data::from("bar"));
+        owner.store(source.port().unwrap(), Ordering::SeqCst);
+
+        let seeds = vec![source.address().clone(), target.address().clone()];
+        let cluster = ClusterClient::with_seeds(source.options(), seeds);
+        cluster.connect().unwrap();
+        assert_eq!(cluster.send_command("GET foo").unwrap(), Data::from("old"));
+
+        // the node goes down and a replica takes its slots over
+        owner.store(target.port().unwrap(), Ordering::SeqCst);
+        drop(source);
+        let err = cluster.send_command("GET foo").unwrap_err();
+        assert!(err.is_connection_error());
+        assert_eq!(
+            cluster.slot_owner(key_slot(b"foo")),
+            Some(target.address().clone())
+        );
+        assert_eq!(cluster.send_command("GET foo").unwrap(), Data::from("bar"));
+    }
+
+    #[test]
+    fn test_assign_splits_ranges() {
+        let cluster = ClusterClient::with_options(ConnectionOptions::default());
+        *cluster.slots.write().unwrap() = vec![SlotRange {
+            start: 0,
+            end: 16383,
+            master: Address::tcp("a", 1),
+        }];
+        cluster.assign(100, Address::tcp("b", 2));
+
+        let ranges: Vec<(u16, u16)> = cluster
+            .slot_ranges()
+            .iter()
+            .map(|range| (range.start, range.end))
+            .collect();
+        assert_eq!(ranges, vec![(0, 99), (100, 100), (101, 16383)]);
+        assert_eq!(cluster.slot_owner(100), Some(Address::tcp("b", 2)));
+        assert_eq!(cluster.slot_owner(101), Some(Address::tcp("a", 1)));
+    }
+}
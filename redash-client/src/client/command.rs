@@ -0,0 +1,174 @@
+use super::errors::RedashError;
+
+/// Splits a command line into arguments the way `redis-cli` does.
+///
+/// Double quoted arguments understand the `\n`, `\r`, `\t`, `\b`, `\a`,
+/// `\\`, `\"` and `\xHH` escapes, single quoted arguments only `\'`.
+/// A closing quote must be followed by whitespace or the end of the line.
+pub fn tokenize(line: &str) -> Result<Vec<Vec<u8>>, RedashError> {
+    let bytes = line.as_bytes();
+    let mut args: Vec<Vec<u8>> = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        while pos < bytes.len() && bytes[pos].is_ascii_whitespace() {
+            pos += 1;
+        }
+        if pos == bytes.len() {
+            return Ok(args);
+        }
+
+        let mut current: Vec<u8> = Vec::new();
+        let mut in_double_quotes = false;
+        let mut in_single_quotes = false;
+
+        loop {
+            let ch = bytes.get(pos).copied();
+            if in_double_quotes {
+                match ch {
+                    None => return Err(unbalanced_quotes()),
+                    Some(b'\\')
+                        if bytes.get(pos + 1) == Some(&b'x')
+                            && hex_byte(bytes.get(pos + 2..pos + 4)).is_some() =>
+                    {
+                        current.extend(hex_byte(bytes.get(pos + 2..pos + 4)));
+                        pos += 3;
+                    }
+                    Some(b'\\') if pos + 1 < bytes.len() => {
+                        pos += 1;
+                        current.push(match bytes[pos] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            other => other,
+                        });
+                    }
+                    Some(b'"') => {
+                        if !ends_argument(bytes.get(pos + 1)) {
+                            return Err(unbalanced_quotes());
+                        }
+                        pos += 1;
+                        break;
+                    }
+                    Some(other) => current.push(other),
+                }
+            } else if in_single_quotes {
+                match ch {
+                    None => return Err(unbalanced_quotes()),
+                    Some(b'\\') if bytes.get(pos + 1) == Some(&b'\'') => {
+                        pos += 1;
+                        current.push(b'\'');
+                    }
+                    Some(b'\'') => {
+                        if !ends_argument(bytes.get(pos + 1)) {
+                            return Err(unbalanced_quotes());
+                        }
+                        pos += 1;
+                        break;
+                    }
+                    Some(other) => current.push(other),
+                }
+            } else {
+                match ch {
+                    None => break,
+                    Some(ch) if ch.is_ascii_whitespace() => break,
+                    Some(b'"') => in_double_quotes = true,
+                    Some(b'\'') => in_single_quotes = true,
+                    Some(other) => current.push(other),
+                }
+            }
+            pos += 1;
+        }
+
+        args.push(current);
+    }
+}
+
+/// Encodes arguments as a RESP array of bulk strings.
+pub fn encode<A: AsRef<[u8]>>(args: &[A]) -> Vec<u8> {
+    let mut buf: Vec<u8> = Vec::new();
+    buf.extend_from_slice(format!("*{}\r\n", args.len()).as_bytes());
+    for arg in args {
+        let arg = arg.as_ref();
+        buf.extend_from_slice(format!("${}\r\n", arg.len()).as_bytes());
+        buf.extend_from_slice(arg);
+        buf.extend_from_slice(b"\r\n");
+    }
+    buf
+}
+
+fn ends_argument(next: Option<&u8>) -> bool {
+    match next {
+        None => true,
+        Some(ch) => ch.is_ascii_whitespace(),
+    }
+}
+
+fn hex_byte(digits: Option<&[u8]>) -> Option<u8> {
+    let digits = digits?;
+    if !digits.iter().all(u8::is_ascii_hexdigit) {
+        return None;
+    }
+    u8::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
+}
+
+fn unbalanced_quotes() -> RedashError {
+    RedashError::OperationError(String::from("invalid command: unbalanced quotes"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(line: &str) -> Vec<Vec<u8>> {
+        tokenize(line).unwrap()
+    }
+
+    #[test]
+    fn test_tokenize_plain() {
+        assert_eq!(
+            args("  SET  k v "),
+            vec![b"SET".to_vec(), b"k".to_vec(), b"v".to_vec()]
+        );
+        assert!(args("   ").is_empty());
+    }
+
+    #[test]
+    fn test_tokenize_quotes() {
+        assert_eq!(
+            args(r#"SET k "hello world""#),
+            vec![b"SET".to_vec(), b"k".to_vec(), b"hello world".to_vec()]
+        );
+        assert_eq!(
+            args(r#"SET k 'it\'s "here"'"#),
+            vec![b"SET".to_vec(), b"k".to_vec(), br#"it's "here""#.to_vec()]
+        );
+        assert_eq!(args(r#"SET k """#)[2], b"".to_vec());
+    }
+
+    #[test]
+    fn test_tokenize_escapes() {
+        assert_eq!(
+            args(r#"SET k "a\x00b\n\"c\"\xzz""#)[2],
+            b"a\x00b\n\"c\"xzz".to_vec()
+        );
+        assert_eq!(args(r"SET k 'a\nb'")[2], br"a\nb".to_vec());
+    }
+
+    #[test]
+    fn test_tokenize_unbalanced() {
+        assert!(tokenize(r#"SET k "abc"#).is_err());
+        assert!(tokenize(r#"SET k 'abc"#).is_err());
+        assert!(tokenize(r#"SET k "abc"def"#).is_err());
+    }
+
+    #[test]
+    fn test_encode() {
+        assert_eq!(
+            encode(&["SET", "k", "hello world"]),
+            b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$11\r\nhello world\r\n".to_vec()
+        );
+    }
+}
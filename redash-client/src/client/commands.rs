@@ -0,0 +1,83 @@
+//! Typed command methods on [`Client`], grouped by data type.
+//!
+//! Each method builds the arguments of one command, sends them through
+//! [`Client::send_args`] and converts the reply with [`FromData`]. Methods
+//! returning user data are generic over the reply type:
+//!
+//! ```no_run
+//! # use redash_client::client::{Client, commands::SetOptions, commands::Expiry};
+//! # let client = Client::new("127.0.0.1", 6379);
+//! client.set("greeting", "hello").unwrap();
+//! let old: Option<String> = client
+//!     .set_with("greeting", "hi", &SetOptions { expiry: Some(Expiry::Ex(60)), get: true, ..SetOptions::default() })
+//!     .unwrap();
+//! let greeting: Option<String> = client.get("greeting").unwrap();
+//! ```
+
+use super::{errors::RedashError, from_data::FromData, parser::Data, Client};
+
+mod hashes;
+mod keys;
+mod lists;
+mod server;
+mod sets;
+mod sorted_sets;
+mod streams;
+mod strings;
+
+pub use keys::{ExpireCondition, ScanOptions};
+pub use sorted_sets::{Limit, ScoreBound};
+pub use streams::StreamEntry;
+pub use strings::{Expiry, SetCondition, SetOptions};
+
+/// Arguments of a command being built.
+struct Command(Vec<Vec<u8>>);
+
+impl Command {
+    fn new(name: &str) -> Self {
+        Command(vec![name.as_bytes().to_vec()])
+    }
+
+    fn arg(mut self, arg: impl AsRef<[u8]>) -> Self {
+        self.0.push(arg.as_ref().to_vec());
+        self
+    }
+
+    fn args<A: AsRef<[u8]>>(mut self, args: &[A]) -> Self {
+        self.0.extend(args.iter().map(|arg| arg.as_ref().to_vec()));
+        self
+    }
+
+    /// Appends a number in its decimal form.
+    fn number(self, number: impl ToString) -> Self {
+        self.arg(number.to_string())
+    }
+
+    fn flag(self, set: bool, flag: &str) -> Self {
+        if set {
+            self.arg(flag)
+        } else {
+            self
+        }
+    }
+
+    fn query<T: FromData>(self, client: &Client) -> Result<T, RedashError> {
+        client.query_args(&self.0)
+    }
+}
+
+/// Members with their scores, from either the flat `member score ...` array
+/// of RESP2 or the `[member, score]` pairs of RESP3.
+fn with_scores<T: FromData>(data: Data) -> Result<Vec<(T, f64)>, RedashError> {
+    match data {
+        Data::Array(items) if items.iter().all(|item| !matches!(item, Data::Array(_))) => {
+            let mut items = items.into_iter();
+            let mut pairs = Vec::with_capacity(items.len() / 2);
+            while let (Some(member), Some(score)) = (items.next(), items.next()) {
+                pairs.push((T::from_data(member)?, f64::from_data(score)?));
+            }
+            Ok(pairs)
+        }
+        data => Vec::from_data(data),
+    }
+}
@@ -0,0 +1,110 @@
+use super::Command;
+use crate::client::{errors::RedashError, from_data::FromData, Client};
+
+impl Client {
+    pub fn hget<T: FromData, K: AsRef<[u8]>, F: AsRef<[u8]>>(
+        &self,
+        key: K,
+        field: F,
+    ) -> Result<T, RedashError> {
+        Command::new("HGET").arg(key).arg(field).query(self)
+    }
+
+    /// Sets fields and returns how many of them were added.
+    pub fn hset<K: AsRef<[u8]>, F: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        key: K,
+        fields: &[(F, V)],
+    ) -> Result<i64, RedashError> {
+        let mut command = Command::new("HSET").arg(key);
+        for (field, value) in fields {
+            command = command.arg(field).arg(value);
+        }
+        command.query(self)
+    }
+
+    /// Sets a field only if it does not exist yet.
+    pub fn hsetnx<K: AsRef<[u8]>, F: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        key: K,
+        field: F,
+        value: V,
+    ) -> Result<bool, RedashError> {
+        Command::new("HSETNX")
+            .arg(key)
+            .arg(field)
+            .arg(value)
+            .query(self)
+    }
+
+    pub fn hmget<T: FromData, K: AsRef<[u8]>, F: AsRef<[u8]>>(
+        &self,
+        key: K,
+        fields: &[F],
+    ) -> Result<T, RedashError> {
+        Command::new("HMGET").arg(key).args(fields).query(self)
+    }
+
+    /// All fields and values, e.g. as a `HashMap<String, String>`.
+    pub fn hgetall<T: FromData, K: AsRef<[u8]>>(&self, key: K) -> Result<T, RedashError> {
+        Command::new("HGETALL").arg(key).query(self)
+    }
+
+    pub fn hdel<K: AsRef<[u8]>, F: AsRef<[u8]>>(
+        &self,
+        key: K,
+        fields: &[F],
+    ) -> Result<i64, RedashError> {
+        Command::new("HDEL").arg(key).args(fields).query(self)
+    }
+
+    pub fn hexists<K: AsRef<[u8]>, F: AsRef<[u8]>>(
+        &self,
+        key: K,
+        field: F,
+    ) -> Result<bool, RedashError> {
+        Command::new("HEXISTS").arg(key).arg(field).query(self)
+    }
+
+    pub fn hincr_by<K: AsRef<[u8]>, F: AsRef<[u8]>>(
+        &self,
+        key: K,
+        field: F,
+        increment: i64,
+    ) -> Result<i64, RedashError> {
+        Command::new("HINCRBY")
+            .arg(key)
+            .arg(field)
+            .number(increment)
+            .query(self)
+    }
+
+    pub fn hlen<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("HLEN").arg(key).query(self)
+    }
+
+    pub fn hkeys<T: FromData, K: AsRef<[u8]>>(&self, key: K) -> Result<T, RedashError> {
+        Command::new("HKEYS").arg(key).query(self)
+    }
+
+    pub fn hvals<T: FromData, K: AsRef<[u8]>>(&self, key: K) -> Result<T, RedashError> {
+        Command::new("HVALS").arg(key).query(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use crate::client::fake_server::FakeServer;
+
+    #[test]
+    fn test_hset_and_hgetall() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        assert_eq!(client.hset("h", &[("a", "1"), ("b", "2")]).unwrap(), 2);
+        let fields: HashMap<String, i64> = client.hgetall("h").unwrap();
+        assert_eq!(fields["b"], 2);
+        assert_eq!(server.commands(), ["HSET h a 1 b 2", "HGETALL h"]);
+    }
+}
@@ -0,0 +1,184 @@
+use super::Command;
+use crate::client::{errors::RedashError, from_data::FromData, Client};
+
+/// When `EXPIRE` and `PEXPIRE` apply the new expiration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExpireCondition {
+    /// Only if the key has no expiration.
+    Nx,
+    /// Only if the key already has an expiration.
+    Xx,
+    /// Only if the new expiration is later than the current one.
+    Gt,
+    /// Only if the new expiration is sooner than the current one.
+    Lt,
+}
+
+impl ExpireCondition {
+    fn as_str(&self) -> &'static str {
+        match self {
+            ExpireCondition::Nx => "NX",
+            ExpireCondition::Xx => "XX",
+            ExpireCondition::Gt => "GT",
+            ExpireCondition::Lt => "LT",
+        }
+    }
+}
+
+/// Filters of `SCAN`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// Glob-style pattern keys must match.
+    pub pattern: Option<String>,
+    /// Hint of how many keys to look at per call.
+    pub count: Option<usize>,
+    /// Only return keys of this type, e.g. `hash`.
+    pub key_type: Option<String>,
+}
+
+fn expire<K: AsRef<[u8]>>(
+    name: &str,
+    key: K,
+    ttl: u64,
+    condition: Option<ExpireCondition>,
+) -> Command {
+    let command = Command::new(name).arg(key).number(ttl);
+    match condition {
+        Some(condition) => command.arg(condition.as_str()),
+        None => command,
+    }
+}
+
+impl Client {
+    /// Deletes keys and returns how many existed.
+    pub fn del<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<i64, RedashError> {
+        Command::new("DEL").args(keys).query(self)
+    }
+
+    /// Number of the given keys that exist, counting repeated keys again.
+    pub fn exists<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<i64, RedashError> {
+        Command::new("EXISTS").args(keys).query(self)
+    }
+
+    /// Sets a time to live in seconds, `false` if the key does not exist or
+    /// the condition was not met.
+    pub fn expire<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        seconds: u64,
+        condition: Option<ExpireCondition>,
+    ) -> Result<bool, RedashError> {
+        expire("EXPIRE", key, seconds, condition).query(self)
+    }
+
+    /// Like [`Client::expire`] in milliseconds.
+    pub fn pexpire<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        millis: u64,
+        condition: Option<ExpireCondition>,
+    ) -> Result<bool, RedashError> {
+        expire("PEXPIRE", key, millis, condition).query(self)
+    }
+
+    /// Removes the expiration, `false` if the key had none.
+    pub fn persist<K: AsRef<[u8]>>(&self, key: K) -> Result<bool, RedashError> {
+        Command::new("PERSIST").arg(key).query(self)
+    }
+
+    /// Seconds left to live, `-1` without expiration and `-2` for a missing key.
+    pub fn ttl<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("TTL").arg(key).query(self)
+    }
+
+    /// Like [`Client::ttl`] in milliseconds.
+    pub fn pttl<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("PTTL").arg(key).query(self)
+    }
+
+    pub fn rename<K: AsRef<[u8]>, N: AsRef<[u8]>>(
+        &self,
+        key: K,
+        new_key: N,
+    ) -> Result<(), RedashError> {
+        Command::new("RENAME").arg(key).arg(new_key).query(self)
+    }
+
+    /// Type of the value stored at the key, `none` if it does not exist.
+    pub fn key_type<K: AsRef<[u8]>>(&self, key: K) -> Result<String, RedashError> {
+        Command::new("TYPE").arg(key).query(self)
+    }
+
+    /// Keys matching a pattern. This blocks the server while it walks every
+    /// key, prefer [`Client::scan`] on large databases.
+    pub fn keys<T: FromData>(&self, pattern: &str) -> Result<T, RedashError> {
+        Command::new("KEYS").arg(pattern).query(self)
+    }
+
+    /// One step of an incremental iteration, starting and ending at cursor
+    /// `0`. Returns the next cursor and a batch of keys.
+    pub fn scan<T: FromData>(
+        &self,
+        cursor: u64,
+        options: &ScanOptions,
+    ) -> Result<(u64, T), RedashError> {
+        let mut command = Command::new("SCAN").number(cursor);
+        if let Some(pattern) = &options.pattern {
+            command = command.arg("MATCH").arg(pattern);
+        }
+        if let Some(count) = options.count {
+            command = command.arg("COUNT").number(count);
+        }
+        if let Some(key_type) = &options.key_type {
+            command = command.arg("TYPE").arg(key_type);
+        }
+        command.query(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{
+        fake_server::{FakeServer, Scripted},
+        parser::Data,
+    };
+
+    #[test]
+    fn test_expire_condition() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        assert!(!client.expire("k", 60, Some(ExpireCondition::Gt)).unwrap());
+        assert!(!client.pexpire("k", 1500, None).unwrap());
+        assert_eq!(server.commands(), ["EXPIRE k 60 GT", "PEXPIRE k 1500"]);
+    }
+
+    #[test]
+    fn test_scan() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        // the server returns every match at once, a cursor shows paging
+        server.script(
+            "SCAN",
+            Scripted::Reply(Data::Array(vec![
+                Data::from("17"),
+                Data::Array(vec![Data::from("a"), Data::from("b")]),
+            ])),
+        );
+        let (cursor, keys): (u64, Vec<String>) = client
+            .scan(
+                0,
+                &ScanOptions {
+                    pattern: Some(String::from("user:*")),
+                    count: Some(100),
+                    ..ScanOptions::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            (cursor, keys),
+            (17, vec![String::from("a"), String::from("b")])
+        );
+        assert_eq!(server.commands(), ["SCAN 0 MATCH user:* COUNT 100"]);
+    }
+}
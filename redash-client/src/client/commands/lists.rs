@@ -0,0 +1,126 @@
+use super::Command;
+use crate::client::{errors::RedashError, from_data::FromData, Client};
+
+impl Client {
+    /// Prepends values and returns the new length of the list.
+    pub fn lpush<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        key: K,
+        values: &[V],
+    ) -> Result<i64, RedashError> {
+        Command::new("LPUSH").arg(key).args(values).query(self)
+    }
+
+    /// Appends values and returns the new length of the list.
+    pub fn rpush<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        key: K,
+        values: &[V],
+    ) -> Result<i64, RedashError> {
+        Command::new("RPUSH").arg(key).args(values).query(self)
+    }
+
+    /// Pops one element, or up to `count` elements as an array.
+    pub fn lpop<T: FromData, K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        count: Option<usize>,
+    ) -> Result<T, RedashError> {
+        let command = Command::new("LPOP").arg(key);
+        match count {
+            Some(count) => command.number(count).query(self),
+            None => command.query(self),
+        }
+    }
+
+    /// Pops one element, or up to `count` elements as an array.
+    pub fn rpop<T: FromData, K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        count: Option<usize>,
+    ) -> Result<T, RedashError> {
+        let command = Command::new("RPOP").arg(key);
+        match count {
+            Some(count) => command.number(count).query(self),
+            None => command.query(self),
+        }
+    }
+
+    /// Blocks until one of the lists has an element, for at most `timeout`
+    /// seconds, `0` waiting forever. The reply is null on timeout and the
+    /// list name with the element otherwise.
+    pub fn blpop<T: FromData, K: AsRef<[u8]>>(
+        &self,
+        keys: &[K],
+        timeout: f64,
+    ) -> Result<T, RedashError> {
+        Command::new("BLPOP").args(keys).number(timeout).query(self)
+    }
+
+    /// Elements from `start` to `stop` inclusive, negative indexes counting
+    /// from the end.
+    pub fn lrange<T: FromData, K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        start: i64,
+        stop: i64,
+    ) -> Result<T, RedashError> {
+        Command::new("LRANGE")
+            .arg(key)
+            .number(start)
+            .number(stop)
+            .query(self)
+    }
+
+    pub fn lindex<T: FromData, K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        index: i64,
+    ) -> Result<T, RedashError> {
+        Command::new("LINDEX").arg(key).number(index).query(self)
+    }
+
+    pub fn llen<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("LLEN").arg(key).query(self)
+    }
+
+    /// Removes `count` occurrences of the value, from the tail when negative
+    /// and all of them when `0`.
+    pub fn lrem<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        key: K,
+        count: i64,
+        value: V,
+    ) -> Result<i64, RedashError> {
+        Command::new("LREM")
+            .arg(key)
+            .number(count)
+            .arg(value)
+            .query(self)
+    }
+
+    pub fn ltrim<K: AsRef<[u8]>>(&self, key: K, start: i64, stop: i64) -> Result<(), RedashError> {
+        Command::new("LTRIM")
+            .arg(key)
+            .number(start)
+            .number(stop)
+            .query(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::fake_server::FakeServer;
+
+    #[test]
+    fn test_pop_with_count() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        client.send_args(&["RPUSH", "l", "a", "b", "c"]).unwrap();
+        let popped: Vec<String> = client.lpop("l", Some(2)).unwrap();
+        assert_eq!(popped, vec!["a", "b"]);
+        let rest: Vec<String> = client.lrange("l", 0, -1).unwrap();
+        assert_eq!(rest, vec!["c"]);
+        assert_eq!(server.commands()[1..], ["LPOP l 2", "LRANGE l 0 -1"]);
+    }
+}
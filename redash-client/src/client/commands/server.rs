@@ -0,0 +1,59 @@
+use super::Command;
+use crate::client::{errors::RedashError, from_data::FromData, Client};
+
+impl Client {
+    pub fn ping(&self) -> Result<String, RedashError> {
+        Command::new("PING").query(self)
+    }
+
+    pub fn echo<T: FromData, M: AsRef<[u8]>>(&self, message: M) -> Result<T, RedashError> {
+        Command::new("ECHO").arg(message).query(self)
+    }
+
+    /// Switches database; the choice is kept across reconnects.
+    pub fn select(&self, database: i64) -> Result<(), RedashError> {
+        Command::new("SELECT").number(database).query(self)
+    }
+
+    /// Number of keys in the selected database.
+    pub fn dbsize(&self) -> Result<i64, RedashError> {
+        Command::new("DBSIZE").query(self)
+    }
+
+    /// Deletes every key of the selected database.
+    pub fn flushdb(&self) -> Result<(), RedashError> {
+        Command::new("FLUSHDB").query(self)
+    }
+
+    /// Deletes every key of every database.
+    pub fn flushall(&self) -> Result<(), RedashError> {
+        Command::new("FLUSHALL").query(self)
+    }
+
+    /// Server information, of one section or of the default ones.
+    pub fn info(&self, section: Option<&str>) -> Result<String, RedashError> {
+        let command = Command::new("INFO");
+        match section {
+            Some(section) => command.arg(section).query(self),
+            None => command.query(self),
+        }
+    }
+
+    /// Server clock as Unix seconds and microseconds.
+    pub fn time(&self) -> Result<(u64, u64), RedashError> {
+        Command::new("TIME").query(self)
+    }
+
+    /// Configuration parameters matching a pattern, with their values.
+    pub fn config_get<T: FromData>(&self, pattern: &str) -> Result<T, RedashError> {
+        Command::new("CONFIG").arg("GET").arg(pattern).query(self)
+    }
+
+    pub fn config_set(&self, parameter: &str, value: &str) -> Result<(), RedashError> {
+        Command::new("CONFIG")
+            .arg("SET")
+            .arg(parameter)
+            .arg(value)
+            .query(self)
+    }
+}
@@ -0,0 +1,50 @@
+use super::Command;
+use crate::client::{errors::RedashError, from_data::FromData, Client};
+
+impl Client {
+    /// Adds members and returns how many were not in the set yet.
+    pub fn sadd<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &self,
+        key: K,
+        members: &[M],
+    ) -> Result<i64, RedashError> {
+        Command::new("SADD").arg(key).args(members).query(self)
+    }
+
+    pub fn srem<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &self,
+        key: K,
+        members: &[M],
+    ) -> Result<i64, RedashError> {
+        Command::new("SREM").arg(key).args(members).query(self)
+    }
+
+    pub fn smembers<T: FromData, K: AsRef<[u8]>>(&self, key: K) -> Result<T, RedashError> {
+        Command::new("SMEMBERS").arg(key).query(self)
+    }
+
+    pub fn sismember<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &self,
+        key: K,
+        member: M,
+    ) -> Result<bool, RedashError> {
+        Command::new("SISMEMBER").arg(key).arg(member).query(self)
+    }
+
+    pub fn scard<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("SCARD").arg(key).query(self)
+    }
+
+    pub fn sinter<T: FromData, K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<T, RedashError> {
+        Command::new("SINTER").args(keys).query(self)
+    }
+
+    pub fn sunion<T: FromData, K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<T, RedashError> {
+        Command::new("SUNION").args(keys).query(self)
+    }
+
+    /// Members of the first set missing from all the others.
+    pub fn sdiff<T: FromData, K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<T, RedashError> {
+        Command::new("SDIFF").args(keys).query(self)
+    }
+}
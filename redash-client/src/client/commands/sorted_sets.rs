@@ -0,0 +1,219 @@
+use std::fmt::Display;
+
+use super::{with_scores, Command};
+use crate::client::{errors::RedashError, from_data::FromData, Client};
+
+/// Bound of a score range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreBound {
+    Inclusive(f64),
+    Exclusive(f64),
+    NegInfinity,
+    PosInfinity,
+}
+
+impl Display for ScoreBound {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScoreBound::Inclusive(score) => write!(f, "{score}"),
+            ScoreBound::Exclusive(score) => write!(f, "({score}"),
+            ScoreBound::NegInfinity => write!(f, "-inf"),
+            ScoreBound::PosInfinity => write!(f, "+inf"),
+        }
+    }
+}
+
+/// Page of a range, skipping `offset` elements and returning at most `count`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limit {
+    pub offset: i64,
+    pub count: i64,
+}
+
+fn by_score<K: AsRef<[u8]>>(
+    key: K,
+    min: ScoreBound,
+    max: ScoreBound,
+    limit: Option<Limit>,
+) -> Command {
+    let command = Command::new("ZRANGEBYSCORE")
+        .arg(key)
+        .number(min)
+        .number(max);
+    match limit {
+        Some(Limit { offset, count }) => command.arg("LIMIT").number(offset).number(count),
+        None => command,
+    }
+}
+
+impl Client {
+    /// Adds members with their scores and returns how many were new.
+    pub fn zadd<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &self,
+        key: K,
+        members: &[(f64, M)],
+    ) -> Result<i64, RedashError> {
+        let mut command = Command::new("ZADD").arg(key);
+        for (score, member) in members {
+            command = command.number(score).arg(member);
+        }
+        command.query(self)
+    }
+
+    pub fn zrem<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &self,
+        key: K,
+        members: &[M],
+    ) -> Result<i64, RedashError> {
+        Command::new("ZREM").arg(key).args(members).query(self)
+    }
+
+    pub fn zscore<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &self,
+        key: K,
+        member: M,
+    ) -> Result<Option<f64>, RedashError> {
+        Command::new("ZSCORE").arg(key).arg(member).query(self)
+    }
+
+    /// Adds to the score of a member and returns the new score.
+    pub fn zincr_by<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &self,
+        key: K,
+        increment: f64,
+        member: M,
+    ) -> Result<f64, RedashError> {
+        Command::new("ZINCRBY")
+            .arg(key)
+            .number(increment)
+            .arg(member)
+            .query(self)
+    }
+
+    pub fn zcard<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("ZCARD").arg(key).query(self)
+    }
+
+    pub fn zcount<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> Result<i64, RedashError> {
+        Command::new("ZCOUNT")
+            .arg(key)
+            .number(min)
+            .number(max)
+            .query(self)
+    }
+
+    /// Rank of a member by ascending score, `None` if it is not in the set.
+    pub fn zrank<K: AsRef<[u8]>, M: AsRef<[u8]>>(
+        &self,
+        key: K,
+        member: M,
+    ) -> Result<Option<i64>, RedashError> {
+        Command::new("ZRANK").arg(key).arg(member).query(self)
+    }
+
+    /// Members by rank from `start` to `stop` inclusive.
+    pub fn zrange<T: FromData, K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        start: i64,
+        stop: i64,
+    ) -> Result<T, RedashError> {
+        Command::new("ZRANGE")
+            .arg(key)
+            .number(start)
+            .number(stop)
+            .query(self)
+    }
+
+    pub fn zrange_with_scores<T: FromData, K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        start: i64,
+        stop: i64,
+    ) -> Result<Vec<(T, f64)>, RedashError> {
+        let data = Command::new("ZRANGE")
+            .arg(key)
+            .number(start)
+            .number(stop)
+            .arg("WITHSCORES")
+            .query(self)?;
+        with_scores(data)
+    }
+
+    /// Members with a score between `min` and `max`, by ascending score.
+    pub fn zrange_by_score<T: FromData, K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        min: ScoreBound,
+        max: ScoreBound,
+        limit: Option<Limit>,
+    ) -> Result<T, RedashError> {
+        by_score(key, min, max, limit).query(self)
+    }
+
+    pub fn zrange_by_score_with_scores<T: FromData, K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        min: ScoreBound,
+        max: ScoreBound,
+        limit: Option<Limit>,
+    ) -> Result<Vec<(T, f64)>, RedashError> {
+        let data = by_score(key, min, max, limit)
+            .arg("WITHSCORES")
+            .query(self)?;
+        with_scores(data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{fake_server::FakeServer, options::ConnectionOptions, ProtocolVersion};
+
+    #[test]
+    fn test_zrange_by_score() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        client
+            .send_args(&["ZADD", "z", "1", "x", "1.5", "a", "2", "b"])
+            .unwrap();
+        let members: Vec<(String, f64)> = client
+            .zrange_by_score_with_scores(
+                "z",
+                ScoreBound::Exclusive(1.0),
+                ScoreBound::PosInfinity,
+                Some(Limit {
+                    offset: 0,
+                    count: 10,
+                }),
+            )
+            .unwrap();
+        assert_eq!(
+            members,
+            vec![(String::from("a"), 1.5), (String::from("b"), 2.0)]
+        );
+        assert_eq!(
+            server.commands()[1..],
+            ["ZRANGEBYSCORE z (1 +inf LIMIT 0 10 WITHSCORES"]
+        );
+    }
+
+    #[test]
+    fn test_resp3_scores() {
+        let server = FakeServer::start().unwrap();
+        let client = Client::with_options(ConnectionOptions {
+            protocol: ProtocolVersion::Resp3,
+            ..server.options()
+        });
+        client.connect().unwrap();
+        client.send_args(&["ZADD", "z", "1.5", "a"]).unwrap();
+
+        let members: Vec<(String, f64)> = client.zrange_with_scores("z", 0, -1).unwrap();
+        assert_eq!(members, vec![(String::from("a"), 1.5)]);
+    }
+}
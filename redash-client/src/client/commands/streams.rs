@@ -0,0 +1,163 @@
+use super::Command;
+use crate::client::{errors::RedashError, from_data::FromData, parser::Data, Client};
+
+/// An entry of a stream with its fields in the order they were added.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEntry {
+    pub id: String,
+    pub fields: Vec<(Vec<u8>, Vec<u8>)>,
+}
+
+impl StreamEntry {
+    /// Value of the first field with that name.
+    pub fn get(&self, field: impl AsRef<[u8]>) -> Option<&[u8]> {
+        self.fields
+            .iter()
+            .find(|(name, _)| name[..] == *field.as_ref())
+            .map(|(_, value)| &value[..])
+    }
+}
+
+impl FromData for StreamEntry {
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        let (id, values): (String, Vec<Vec<u8>>) = FromData::from_data(data)?;
+        let mut values = values.into_iter();
+        let mut fields = Vec::with_capacity(values.len() / 2);
+        while let (Some(name), Some(value)) = (values.next(), values.next()) {
+            fields.push((name, value));
+        }
+        Ok(StreamEntry { id, fields })
+    }
+}
+
+impl Client {
+    /// Appends an entry and returns its ID; `id` is usually `*` to let the
+    /// server generate it.
+    pub fn xadd<K: AsRef<[u8]>, F: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        key: K,
+        id: &str,
+        fields: &[(F, V)],
+    ) -> Result<String, RedashError> {
+        let mut command = Command::new("XADD").arg(key).arg(id);
+        for (field, value) in fields {
+            command = command.arg(field).arg(value);
+        }
+        command.query(self)
+    }
+
+    pub fn xlen<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("XLEN").arg(key).query(self)
+    }
+
+    /// Entries with IDs between `start` and `end`, `-` and `+` standing for
+    /// the smallest and largest IDs.
+    pub fn xrange<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        start: &str,
+        end: &str,
+        count: Option<usize>,
+    ) -> Result<Vec<StreamEntry>, RedashError> {
+        let command = Command::new("XRANGE").arg(key).arg(start).arg(end);
+        match count {
+            Some(count) => command.arg("COUNT").number(count).query(self),
+            None => command.query(self),
+        }
+    }
+
+    /// Like [`Client::xrange`] from `end` down to `start`.
+    pub fn xrevrange<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        end: &str,
+        start: &str,
+        count: Option<usize>,
+    ) -> Result<Vec<StreamEntry>, RedashError> {
+        let command = Command::new("XREVRANGE").arg(key).arg(end).arg(start);
+        match count {
+            Some(count) => command.arg("COUNT").number(count).query(self),
+            None => command.query(self),
+        }
+    }
+
+    /// Reads entries after the given IDs of each stream, waiting up to
+    /// `block` milliseconds when there are none. Streams without new entries
+    /// are left out, so the result is empty on timeout.
+    pub fn xread<K: AsRef<[u8]>>(
+        &self,
+        streams: &[(K, &str)],
+        count: Option<usize>,
+        block: Option<u64>,
+    ) -> Result<Vec<(String, Vec<StreamEntry>)>, RedashError> {
+        let mut command = Command::new("XREAD");
+        if let Some(count) = count {
+            command = command.arg("COUNT").number(count);
+        }
+        if let Some(block) = block {
+            command = command.arg("BLOCK").number(block);
+        }
+        command = command.arg("STREAMS");
+        for (key, _) in streams {
+            command = command.arg(key);
+        }
+        for (_, id) in streams {
+            command = command.arg(id);
+        }
+        command.query(self)
+    }
+
+    pub fn xdel<K: AsRef<[u8]>>(&self, key: K, ids: &[&str]) -> Result<i64, RedashError> {
+        Command::new("XDEL").arg(key).args(ids).query(self)
+    }
+
+    /// Trims the stream to `maxlen` entries, `approximate` letting the
+    /// server keep a few more for efficiency. Returns the entries removed.
+    pub fn xtrim<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        maxlen: usize,
+        approximate: bool,
+    ) -> Result<i64, RedashError> {
+        Command::new("XTRIM")
+            .arg(key)
+            .arg("MAXLEN")
+            .arg(if approximate { "~" } else { "=" })
+            .number(maxlen)
+            .query(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::client::{
+        fake_server::{FakeServer, Scripted},
+        parser::Data,
+    };
+
+    #[test]
+    fn test_xread() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        // streams are not implemented by the server
+        server.script(
+            "XREAD",
+            Scripted::Reply(Data::Array(vec![Data::Array(vec![
+                Data::from("s"),
+                Data::Array(vec![Data::Array(vec![
+                    Data::from("1-0"),
+                    Data::Array(vec![Data::from("f"), Data::from("v")]),
+                ])]),
+            ])])),
+        );
+        let streams = client
+            .xread(&[("s", "0"), ("t", "$")], Some(5), None)
+            .unwrap();
+        assert_eq!(streams.len(), 1);
+        let (key, entries) = &streams[0];
+        assert_eq!(key, "s");
+        assert_eq!(entries[0].id, "1-0");
+        assert_eq!(entries[0].get("f"), Some(&b"v"[..]));
+        assert_eq!(server.commands(), ["XREAD COUNT 5 STREAMS s t 0 $"]);
+    }
+}
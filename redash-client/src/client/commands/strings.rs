@@ -0,0 +1,189 @@
+use super::Command;
+use crate::client::{errors::RedashError, from_data::FromData, Client};
+
+/// Expiration set by [`Client::set_with`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expiry {
+    /// Seconds from now.
+    Ex(u64),
+    /// Milliseconds from now.
+    Px(u64),
+    /// Unix time in seconds.
+    ExAt(u64),
+    /// Unix time in milliseconds.
+    PxAt(u64),
+    /// Keeps the time to live of the previous value.
+    KeepTtl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetCondition {
+    /// Only set the key if it does not exist.
+    Nx,
+    /// Only set the key if it already exists.
+    Xx,
+}
+
+/// Options of `SET`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SetOptions {
+    pub expiry: Option<Expiry>,
+    pub condition: Option<SetCondition>,
+    /// Return the previous value instead of `OK`.
+    pub get: bool,
+}
+
+impl Client {
+    pub fn get<T: FromData, K: AsRef<[u8]>>(&self, key: K) -> Result<T, RedashError> {
+        Command::new("GET").arg(key).query(self)
+    }
+
+    pub fn set<K: AsRef<[u8]>, V: AsRef<[u8]>>(&self, key: K, value: V) -> Result<(), RedashError> {
+        Command::new("SET").arg(key).arg(value).query(self)
+    }
+
+    /// `SET` with options. The reply is `OK` or the previous value with
+    /// `get`, and null when a condition prevented the write: read it as
+    /// `Option<()>` or `Option<String>`.
+    pub fn set_with<T: FromData, K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: V,
+        options: &SetOptions,
+    ) -> Result<T, RedashError> {
+        let mut command = Command::new("SET").arg(key).arg(value);
+        command = match options.condition {
+            Some(SetCondition::Nx) => command.arg("NX"),
+            Some(SetCondition::Xx) => command.arg("XX"),
+            None => command,
+        };
+        command = command.flag(options.get, "GET");
+        command = match options.expiry {
+            Some(Expiry::Ex(seconds)) => command.arg("EX").number(seconds),
+            Some(Expiry::Px(millis)) => command.arg("PX").number(millis),
+            Some(Expiry::ExAt(seconds)) => command.arg("EXAT").number(seconds),
+            Some(Expiry::PxAt(millis)) => command.arg("PXAT").number(millis),
+            Some(Expiry::KeepTtl) => command.arg("KEEPTTL"),
+            None => command,
+        };
+        command.query(self)
+    }
+
+    pub fn getdel<T: FromData, K: AsRef<[u8]>>(&self, key: K) -> Result<T, RedashError> {
+        Command::new("GETDEL").arg(key).query(self)
+    }
+
+    pub fn mget<T: FromData, K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<T, RedashError> {
+        Command::new("MGET").args(keys).query(self)
+    }
+
+    pub fn mset<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        pairs: &[(K, V)],
+    ) -> Result<(), RedashError> {
+        let mut command = Command::new("MSET");
+        for (key, value) in pairs {
+            command = command.arg(key).arg(value);
+        }
+        command.query(self)
+    }
+
+    pub fn incr<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("INCR").arg(key).query(self)
+    }
+
+    pub fn incr_by<K: AsRef<[u8]>>(&self, key: K, increment: i64) -> Result<i64, RedashError> {
+        Command::new("INCRBY")
+            .arg(key)
+            .number(increment)
+            .query(self)
+    }
+
+    pub fn incr_by_float<K: AsRef<[u8]>>(
+        &self,
+        key: K,
+        increment: f64,
+    ) -> Result<f64, RedashError> {
+        Command::new("INCRBYFLOAT")
+            .arg(key)
+            .number(increment)
+            .query(self)
+    }
+
+    pub fn decr<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("DECR").arg(key).query(self)
+    }
+
+    pub fn decr_by<K: AsRef<[u8]>>(&self, key: K, decrement: i64) -> Result<i64, RedashError> {
+        Command::new("DECRBY")
+            .arg(key)
+            .number(decrement)
+            .query(self)
+    }
+
+    /// Appends to the value and returns its new length.
+    pub fn append<K: AsRef<[u8]>, V: AsRef<[u8]>>(
+        &self,
+        key: K,
+        value: V,
+    ) -> Result<i64, RedashError> {
+        Command::new("APPEND").arg(key).arg(value).query(self)
+    }
+
+    pub fn strlen<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, RedashError> {
+        Command::new("STRLEN").arg(key).query(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::fake_server::FakeServer;
+
+    #[test]
+    fn test_set_options() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        client.send_args(&["SET", "k", "old"]).unwrap();
+        let old: Option<String> = client
+            .set_with(
+                "k",
+                "v",
+                &SetOptions {
+                    expiry: Some(Expiry::Px(1500)),
+                    condition: Some(SetCondition::Xx),
+                    get: true,
+                },
+            )
+            .unwrap();
+        assert_eq!(old.as_deref(), Some("old"));
+
+        let _: Option<()> = client
+            .set_with(
+                "k",
+                "v",
+                &SetOptions {
+                    expiry: Some(Expiry::KeepTtl),
+                    ..SetOptions::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(
+            server.commands()[1..],
+            ["SET k v XX GET PX 1500", "SET k v KEEPTTL"]
+        );
+    }
+
+    #[test]
+    fn test_mset_and_incr_by_float() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        client.send_args(&["SET", "n", "2.5"]).unwrap();
+        assert_eq!(client.incr_by_float("n", 0.25).unwrap(), 2.75);
+        client.mset(&[("a", "1"), ("b", "2")]).unwrap();
+        assert_eq!(
+            server.commands()[1..],
+            ["INCRBYFLOAT n 0.25", "MSET a 1 b 2"]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use std::{cell::Cell, io::Write, time::Duration};
+
+use super::{
+    command,
+    errors::RedashError,
+    parser::{Data, Parser, ParserLimits},
+    transport::Transport,
+};
+
+/// A single server connection.
+///
+/// The parser owns the buffered reader for the whole lifetime of the
+/// connection, so bytes read ahead while parsing one reply are kept for the
+/// next one. Requests are written to the same stream through the parser.
+pub struct Connection {
+    parser: Parser<Transport>,
+    state: Cell<SessionState>,
+}
+
+/// Server side state left behind by earlier commands, tracked so that a
+/// connection is not handed to an unrelated caller in that state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SessionState {
+    /// `MULTI` was sent without a matching `EXEC` or `DISCARD`.
+    pub in_transaction: bool,
+    /// Keys are `WATCH`ed.
+    pub watching: bool,
+    /// At least one channel, pattern or shard channel is subscribed.
+    pub subscribed: bool,
+}
+
+impl SessionState {
+    pub fn is_dirty(&self) -> bool {
+        self.in_transaction || self.watching || self.subscribed
+    }
+
+    fn track<A: AsRef<[u8]>>(&mut self, args: &[A]) {
+        let name = match args.first() {
+            Some(name) => name.as_ref().to_ascii_uppercase(),
+            None => return,
+        };
+        match &name[..] {
+            b"MULTI" => self.in_transaction = true,
+            b"EXEC" | b"DISCARD" => {
+                self.in_transaction = false;
+                self.watching = false;
+            }
+            b"WATCH" => self.watching = true,
+            b"UNWATCH" => self.watching = false,
+            b"SUBSCRIBE" | b"PSUBSCRIBE" | b"SSUBSCRIBE" => self.subscribed = true,
+            b"RESET" => *self = SessionState::default(),
+            _ => (),
+        }
+    }
+
+    /// Follows the subscription count carried by (un)subscribe confirmations.
+    fn track_reply(&mut self, reply: &Data) {
+        let items = match reply {
+            Data::Array(items) | Data::Push(items) => items,
+            _ => return,
+        };
+        let kind = items.first().and_then(|kind| kind.as_bytes());
+        if let (Some(kind), Some(Data::Integer(count))) = (kind, items.get(2)) {
+            if kind.ends_with(b"subscribe") {
+                self.subscribed = *count > 0;
+            }
+        }
+    }
+}
+
+impl Connection {
+    pub fn new(transport: impl Into<Transport>) -> Self {
+        Connection::with_limits(transport, ParserLimits::default())
+    }
+
+    pub fn with_limits(transport: impl Into<Transport>, limits: ParserLimits) -> Self {
+        Connection {
+            parser: Parser::with_limits(transport.into(), limits),
+            state: Cell::new(SessionState::default()),
+        }
+    }
+
+    /// Writes one encoded command without waiting for its reply.
+    pub fn send<A: AsRef<[u8]>>(&self, args: &[A]) -> Result<(), RedashError> {
+        self.track(args);
+        self.write(&command::encode(args))
+    }
+
+    /// Writes several commands with a single write, without waiting for
+    /// their replies.
+    pub fn send_all<A: AsRef<[u8]>>(&self, commands: &[Vec<A>]) -> Result<(), RedashError> {
+        let mut msg: Vec<u8> = Vec::new();
+        for args in commands {
+            self.track(args);
+            msg.extend(command::encode(args));
+        }
+        self.write(&msg)
+    }
+
+    /// Writes raw, already encoded bytes and flushes them.
+    pub fn write(&self, bytes: &[u8]) -> Result<(), RedashError> {
+        let mut stream = self.parser.get_mut();
+        stream.write_all(bytes).map_err(RedashError::IOError)?;
+        stream.flush().map_err(RedashError::IOError)
+    }
+
+    /// Reads the next reply from the connection.
+    pub fn read(&self) -> Result<Data, RedashError> {
+        let reply = self.parser.next()?;
+        let mut state = self.state.get();
+        state.track_reply(&reply);
+        self.state.set(state);
+        Ok(reply)
+    }
+
+    /// Sends one command and reads its reply.
+    pub fn request<A: AsRef<[u8]>>(&self, args: &[A]) -> Result<Data, RedashError> {
+        self.send(args)?;
+        self.read()
+    }
+
+    /// Changes how long reading a reply waits, `None` to wait forever.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> Result<(), RedashError> {
+        self.parser
+            .get_mut()
+            .set_read_timeout(timeout)
+            .map_err(RedashError::IOError)
+    }
+
+    /// State left on the server by the commands sent so far.
+    pub fn state(&self) -> SessionState {
+        self.state.get()
+    }
+
+    fn track<A: AsRef<[u8]>>(&self, args: &[A]) {
+        let mut state = self.state.get();
+        state.track(args);
+        self.state.set(state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpStream;
+
+    use super::*;
+    use crate::client::{
+        fake_server::{FakeServer, Scripted},
+        options::Address,
+    };
+
+    #[test]
+    fn test_back_to_back_replies_are_kept() {
+        let server = FakeServer::start().unwrap();
+        // both replies arrive in one segment, the second is read ahead
+        server.script("PING", Scripted::Raw(b"+PONG\r\n:42\r\n".to_vec()));
+        let stream = match server.address() {
+            Address::Tcp { host, port } => TcpStream::connect((&host[..], *port)).unwrap(),
+            Address::Unix(_) => unreachable!("the server listens on TCP"),
+        };
+
+        let connection = Connection::new(stream);
+        assert_eq!(connection.request(&["PING"]).unwrap(), Data::from("PONG"));
+        assert_eq!(connection.read().unwrap(), Data::Integer(42));
+    }
+
+    #[test]
+    fn test_session_state_follows_commands_and_confirmations() {
+        let mut state = SessionState::default();
+        state.track(&["watch", "a"]);
+        state.track(&["MULTI"]);
+        assert!(state.in_transaction && state.watching && state.is_dirty());
+        state.track(&["EXEC"]);
+        assert!(!state.is_dirty());
+
+        state.track(&["SUBSCRIBE", "a", "b"]);
+        let confirmation = |kind: &str, count| {
+            Data::Array(vec![
+                Data::from(kind),
+                Data::from("a"),
+                Data::Integer(count),
+            ])
+        };
+        state.track_reply(&confirmation("subscribe", 2));
+        state.track_reply(&confirmation("unsubscribe", 1));
+        assert!(state.subscribed);
+        state.track_reply(&confirmation("unsubscribe", 0));
+        assert!(!state.is_dirty());
+    }
+}
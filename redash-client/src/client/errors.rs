@@ -1,26 +1,264 @@
 use core::fmt;
 use std::{error::Error, io};
 
+/// An error reply such as `-WRONGTYPE Operation against a key holding the
+/// wrong kind of value`, split into its code and message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplyError {
+    code: String,
+    message: String,
+}
+
+/// Where a `MOVED` or `ASK` redirect points to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedirectTarget {
+    pub slot: u16,
+    /// Empty when the target is on the host of the node that replied.
+    pub host: String,
+    pub port: u16,
+}
+
+impl ReplyError {
+    /// Splits an error line on its first space, the code being the first word.
+    pub fn new(line: &str) -> Self {
+        let (code, message) = line.split_once(' ').unwrap_or((line, ""));
+        ReplyError {
+            code: String::from(code),
+            message: String::from(message),
+        }
+    }
+
+    /// Error code prefix, e.g. `ERR`, `WRONGTYPE` or `MOVED`.
+    pub fn code(&self) -> &str {
+        &self.code
+    }
+
+    /// The rest of the line after the code.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn is_moved(&self) -> bool {
+        self.code == "MOVED"
+    }
+
+    pub fn is_ask(&self) -> bool {
+        self.code == "ASK"
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        self.is_moved() || self.is_ask()
+    }
+
+    pub fn is_wrong_type(&self) -> bool {
+        self.code == "WRONGTYPE"
+    }
+
+    /// A script or function is running and blocks the server.
+    pub fn is_busy(&self) -> bool {
+        self.code == "BUSY"
+    }
+
+    /// The dataset is still being loaded in memory.
+    pub fn is_loading(&self) -> bool {
+        self.code == "LOADING"
+    }
+
+    /// A write was sent to a read only replica.
+    pub fn is_readonly(&self) -> bool {
+        self.code == "READONLY"
+    }
+
+    pub fn is_auth_error(&self) -> bool {
+        matches!(&self.code[..], "NOAUTH" | "WRONGPASS")
+    }
+
+    pub fn is_no_script(&self) -> bool {
+        self.code == "NOSCRIPT"
+    }
+
+    /// `EXEC` discarded a transaction because a queued command was rejected.
+    pub fn is_exec_abort(&self) -> bool {
+        self.code == "EXECABORT"
+    }
+
+    /// A cluster or replication condition that goes away when retrying later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(
+            &self.code[..],
+            "BUSY" | "LOADING" | "TRYAGAIN" | "CLUSTERDOWN" | "MASTERDOWN"
+        )
+    }
+
+    /// Target of a `MOVED <slot> <host>:<port>` or `ASK ...` redirect.
+    pub fn redirect(&self) -> Option<RedirectTarget> {
+        if !self.is_redirect() {
+            return None;
+        }
+        let (slot, endpoint) = self.message.split_once(' ')?;
+        let (host, port) = endpoint.rsplit_once(':')?;
+        Some(RedirectTarget {
+            slot: slot.parse().ok()?,
+            host: String::from(host),
+            port: port.parse().ok()?,
+        })
+    }
+}
+
+impl fmt::Display for ReplyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.message.is_empty() {
+            write!(f, "{}", self.code)
+        } else {
+            write!(f, "{} {}", self.code, self.message)
+        }
+    }
+}
+
 #[derive(Debug)]
 pub enum RedashError {
-    DataError(String),
+    /// Error reply sent by the server.
+    DataError(ReplyError),
+    /// `NOAUTH` reply, the server requires authentication.
+    AuthRequired(ReplyError),
+    /// `WRONGPASS` reply, the credentials were rejected.
+    AuthFailed(ReplyError),
     IOError(io::Error),
-    UnknownError(Box<dyn Error>),
+    UnknownError(Box<dyn Error + Send + Sync>),
     ServerError(String, u8),
     OperationError(String),
+    /// Every attempt of a `transaction_with_retry` was aborted because a
+    /// `WATCH`ed key changed.
+    WatchConflict {
+        attempts: usize,
+    },
+    /// A reply could not be converted to the requested type.
+    TypeError {
+        expected: &'static str,
+        actual: String,
+    },
+}
+
+impl RedashError {
+    /// Builds the error for an error reply sent by the server.
+    pub fn from_reply(line: &str) -> Self {
+        let reply = ReplyError::new(line);
+        match reply.code() {
+            "NOAUTH" => RedashError::AuthRequired(reply),
+            "WRONGPASS" => RedashError::AuthFailed(reply),
+            _ => RedashError::DataError(reply),
+        }
+    }
+
+    /// Whether the connection can no longer be used after this error, because
+    /// it broke, timed out or received data that could not be parsed.
+    pub fn is_connection_error(&self) -> bool {
+        matches!(
+            self,
+            RedashError::IOError(_) | RedashError::UnknownError(_) | RedashError::ServerError(..)
+        )
+    }
+
+    /// The error reply sent by the server, `None` for connection or protocol
+    /// errors after which the connection can no longer be trusted.
+    pub fn reply(&self) -> Option<&ReplyError> {
+        match self {
+            RedashError::DataError(reply)
+            | RedashError::AuthRequired(reply)
+            | RedashError::AuthFailed(reply) => Some(reply),
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for RedashError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match &self {
             RedashError::DataError(err) => write!(f, "{err}"),
+            RedashError::AuthRequired(err) => write!(f, "{err}"),
+            RedashError::AuthFailed(err) => write!(f, "{err}"),
             RedashError::IOError(err) => write!(f, "{err}"),
             RedashError::UnknownError(err) => write!(f, "{err}"),
             RedashError::ServerError(err, u) => {
                 write!(f, "{err} - Server response: {}", *u as char)
             }
             RedashError::OperationError(err) => write!(f, "{err}"),
+            RedashError::WatchConflict { attempts } => {
+                write!(
+                    f,
+                    "watched keys changed during {attempts} transaction attempts"
+                )
+            }
+            RedashError::TypeError { expected, actual } => {
+                write!(f, "cannot convert {actual} reply to {expected}")
+            }
+        }
+    }
+}
+impl Error for RedashError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RedashError::IOError(err) => Some(err),
+            RedashError::UnknownError(err) => Some(&**err),
+            _ => None,
         }
     }
 }
-impl Error for RedashError {}
+
+impl From<io::Error> for RedashError {
+    fn from(err: io::Error) -> Self {
+        RedashError::IOError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reply_error_parts() {
+        let err = RedashError::from_reply(
+            "WRONGTYPE Operation against a key holding the wrong kind of value",
+        );
+        let reply = err.reply().unwrap();
+        assert_eq!(reply.code(), "WRONGTYPE");
+        assert_eq!(
+            reply.message(),
+            "Operation against a key holding the wrong kind of value"
+        );
+        assert!(reply.is_wrong_type() && !reply.is_retryable());
+        assert_eq!(
+            err.to_string(),
+            "WRONGTYPE Operation against a key holding the wrong kind of value"
+        );
+
+        let reply = ReplyError::new("LOADING");
+        assert_eq!((reply.code(), reply.message()), ("LOADING", ""));
+        assert!(reply.is_loading() && reply.is_retryable());
+    }
+
+    #[test]
+    fn test_redirect_targets() {
+        let moved = ReplyError::new("MOVED 3999 10.0.0.2:6379");
+        assert!(moved.is_moved() && moved.is_redirect());
+        assert_eq!(
+            moved.redirect(),
+            Some(RedirectTarget {
+                slot: 3999,
+                host: String::from("10.0.0.2"),
+                port: 6379,
+            })
+        );
+        let ask = ReplyError::new("ASK 12182 :6380");
+        assert!(ask.is_ask());
+        assert_eq!(ask.redirect().unwrap().host, "");
+        assert_eq!(ReplyError::new("ERR MOVED 1 a:1").redirect(), None);
+    }
+
+    #[test]
+    fn test_io_error_source() {
+        let err: RedashError = io::Error::new(io::ErrorKind::TimedOut, "timed out").into();
+        assert_eq!(err.source().unwrap().to_string(), "timed out");
+        assert!(RedashError::from_reply("ERR no").source().is_none());
+    }
+}
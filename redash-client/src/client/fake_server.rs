@@ -0,0 +1,1148 @@
+//! In-process server speaking RESP, to test against without a real Redis.
+//!
+//! [`FakeServer`] keeps its data in memory and implements the common key,
+//! string, hash, list, set and sorted set commands, along with what the
+//! client sends on its own: `HELLO`, `AUTH`, `CLIENT SETNAME`, `SELECT` and
+//! `MULTI`/`EXEC` with `WATCH`, as well as Pub/Sub on channels and
+//! patterns. Replies can be scripted and errors injected per command.
+//! Blocking commands never block.
+//!
+//! Other crates enable it with the `test-server` feature, e.g. as a
+//! dev-dependency:
+//!
+//! ```no_run
+//! use redash_client::client::fake_server::FakeServer;
+//!
+//! let server = FakeServer::start().unwrap();
+//! let client = server.connect().unwrap();
+//! client.set("greeting", "hello").unwrap();
+//!
+//! server.inject_error("GET", "ERR injected");
+//! assert!(client.get::<String, _>("greeting").is_err());
+//! ```
+
+mod commands;
+mod store;
+
+use std::{
+    collections::{BTreeSet, HashMap, VecDeque},
+    io::{self, Read, Write},
+    net::{Shutdown, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+#[cfg(unix)]
+use std::{
+    os::unix::net::{UnixListener, UnixStream},
+    path::Path,
+};
+
+use super::{
+    errors::RedashError,
+    options::{Address, ConnectionOptions},
+    parser::{Data, Parser},
+    Client, ProtocolVersion,
+};
+use store::{glob_match, Bytes, Db, Value};
+
+const DATABASES: usize = 16;
+
+/// What the server does with a command instead of running it.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Scripted {
+    /// Answers with this reply, encoded for the protocol of the connection.
+    Reply(Data),
+    /// Writes these bytes as they are, e.g. a malformed or partial reply.
+    Raw(Vec<u8>),
+    /// Closes the connection without answering.
+    Disconnect,
+}
+
+type Handler = Arc<dyn Fn(&[Vec<u8>]) -> Scripted + Send + Sync>;
+
+/// In-process server listening on an ephemeral port or a Unix socket,
+/// stopped when dropped.
+pub struct FakeServer {
+    address: Address,
+    shared: Arc<Shared>,
+}
+
+struct Shared {
+    state: Mutex<State>,
+    stopped: AtomicBool,
+    // clones of the open connections, to close them from the outside
+    connections: Mutex<HashMap<i64, Socket>>,
+}
+
+struct State {
+    dbs: Vec<Db>,
+    password: Option<String>,
+    scripts: HashMap<String, VecDeque<Scripted>>,
+    handlers: HashMap<String, Handler>,
+    commands: Vec<String>,
+    next_client_id: i64,
+    // by connection id
+    subscriptions: HashMap<i64, Subscriptions>,
+    // encoded messages for other connections, written once the command ran
+    outbox: Vec<(i64, Vec<u8>)>,
+}
+
+/// Channels and patterns a connection subscribed to.
+#[derive(Default)]
+struct Subscriptions {
+    protocol: ProtocolVersion,
+    channels: BTreeSet<Bytes>,
+    patterns: BTreeSet<Bytes>,
+}
+
+impl Subscriptions {
+    fn count(&self) -> i64 {
+        (self.channels.len() + self.patterns.len()) as i64
+    }
+}
+
+/// Per connection state.
+struct Session {
+    id: i64,
+    protocol: ProtocolVersion,
+    db: usize,
+    authenticated: bool,
+    name: Option<Bytes>,
+    // commands queued since MULTI
+    queued: Option<Vec<Vec<Bytes>>>,
+    // a command failed to queue, EXEC then discards the transaction
+    aborted: bool,
+    // values of the WATCHed keys, EXEC fails once one of them changed
+    watched: Vec<(usize, Bytes, Option<Value>)>,
+    // frames written ahead of the reply, e.g. one per subscribed channel
+    pending: Vec<Data>,
+    quit: bool,
+}
+
+impl Session {
+    /// Replies with several frames, the last one answering the command.
+    fn frames(&mut self, mut frames: Vec<Data>) -> Data {
+        let last = frames.pop().unwrap_or(Data::Null);
+        self.pending.extend(frames);
+        last
+    }
+
+    fn new(id: i64) -> Self {
+        Session {
+            id,
+            protocol: ProtocolVersion::Resp2,
+            db: 0,
+            authenticated: false,
+            name: None,
+            queued: None,
+            aborted: false,
+            watched: Vec::new(),
+            pending: Vec::new(),
+            quit: false,
+        }
+    }
+}
+
+enum Listener {
+    Tcp(TcpListener),
+    #[cfg(unix)]
+    Unix(UnixListener),
+}
+
+impl Listener {
+    fn accept(&self) -> io::Result<Socket> {
+        match self {
+            Listener::Tcp(listener) => listener.accept().map(|(stream, _)| Socket::Tcp(stream)),
+            #[cfg(unix)]
+            Listener::Unix(listener) => listener.accept().map(|(stream, _)| Socket::Unix(stream)),
+        }
+    }
+}
+
+enum Socket {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+impl Socket {
+    fn try_clone(&self) -> io::Result<Socket> {
+        match self {
+            Socket::Tcp(stream) => stream.try_clone().map(Socket::Tcp),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.try_clone().map(Socket::Unix),
+        }
+    }
+
+    fn shutdown(&self) {
+        // the peer may already be gone
+        let _ = match self {
+            Socket::Tcp(stream) => stream.shutdown(Shutdown::Both),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.shutdown(Shutdown::Both),
+        };
+    }
+}
+
+impl Read for Socket {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Socket::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Socket {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Socket::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Socket::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Socket::Unix(stream) => stream.flush(),
+        }
+    }
+}
+
+impl FakeServer {
+    /// Starts a server on an ephemeral port of `127.0.0.1`.
+    pub fn start() -> io::Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let port = listener.local_addr()?.port();
+        Ok(FakeServer::serve(
+            Listener::Tcp(listener),
+            Address::tcp("127.0.0.1", port),
+        ))
+    }
+
+    /// Starts a server on a Unix socket, removed again when the server is
+    /// dropped.
+    #[cfg(unix)]
+    pub fn start_unix(path: impl AsRef<Path>) -> io::Result<Self> {
+        let path = path.as_ref();
+        let listener = UnixListener::bind(path)?;
+        Ok(FakeServer::serve(
+            Listener::Unix(listener),
+            Address::Unix(path.to_path_buf()),
+        ))
+    }
+
+    fn serve(listener: Listener, address: Address) -> Self {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                dbs: (0..DATABASES).map(|_| Db::default()).collect(),
+                password: None,
+                scripts: HashMap::new(),
+                handlers: HashMap::new(),
+                commands: Vec::new(),
+                next_client_id: 1,
+                subscriptions: HashMap::new(),
+                outbox: Vec::new(),
+            }),
+            stopped: AtomicBool::new(false),
+            connections: Mutex::new(HashMap::new()),
+        });
+        let accepting = shared.clone();
+        thread::spawn(move || {
+            while let Ok(socket) = listener.accept() {
+                if accepting.stopped.load(Ordering::SeqCst) {
+                    return;
+                }
+                let shared = accepting.clone();
+                thread::spawn(move || shared.serve_connection(socket));
+            }
+        });
+        FakeServer { address, shared }
+    }
+
+    pub fn address(&self) -> &Address {
+        &self.address
+    }
+
+    /// Port the server listens on, `None` on a Unix socket.
+    pub fn port(&self) -> Option<u16> {
+        match self.address {
+            Address::Tcp { port, .. } => Some(port),
+            Address::Unix(_) => None,
+        }
+    }
+
+    /// Options to connect to the server, to adjust before creating a client.
+    pub fn options(&self) -> ConnectionOptions {
+        ConnectionOptions {
+            address: self.address.clone(),
+            ..ConnectionOptions::default()
+        }
+    }
+
+    /// A client connected with the default [`options`](Self::options).
+    pub fn connect(&self) -> Result<Client, RedashError> {
+        let client = Client::with_options(self.options());
+        client.connect()?;
+        Ok(client)
+    }
+
+    /// Requires clients to authenticate as the default user with this
+    /// password, like `requirepass`.
+    pub fn require_password(&self, password: &str) {
+        self.shared.lock().password = Some(String::from(password));
+    }
+
+    /// Queues what the server does with the next command of that name
+    /// instead of running it, e.g. `GET`. Scripts for the same command are
+    /// used in the order they were queued, before any handler.
+    pub fn script(&self, command: &str, scripted: Scripted) {
+        self.shared
+            .lock()
+            .scripts
+            .entry(command.to_ascii_uppercase())
+            .or_default()
+            .push_back(scripted);
+    }
+
+    /// Fails the next command of that name with an error reply, e.g.
+    /// `"READONLY You can't write against a read only replica."`.
+    pub fn inject_error(&self, command: &str, error: &str) {
+        self.script(command, Scripted::Reply(Data::Error(String::from(error))));
+    }
+
+    /// Answers every command of that name with `handler`, called with the
+    /// arguments of the command including its name, instead of running it.
+    pub fn handle<F>(&self, command: &str, handler: F)
+    where
+        F: Fn(&[Vec<u8>]) -> Scripted + Send + Sync + 'static,
+    {
+        self.shared
+            .lock()
+            .handlers
+            .insert(command.to_ascii_uppercase(), Arc::new(handler));
+    }
+
+    /// Commands received so far from every connection, as text with the
+    /// arguments separated by spaces.
+    pub fn commands(&self) -> Vec<String> {
+        self.shared.lock().commands.clone()
+    }
+
+    /// Closes every open connection, as when the server restarts. The data
+    /// is kept. A connection is only known once the server accepted it,
+    /// which a first command makes sure of.
+    pub fn disconnect_all(&self) {
+        self.shared.lock().subscriptions.clear();
+        let connections = std::mem::take(&mut *lock(&self.shared.connections));
+        for socket in connections.values() {
+            socket.shutdown();
+        }
+    }
+}
+
+impl Drop for FakeServer {
+    fn drop(&mut self) {
+        self.shared.stopped.store(true, Ordering::SeqCst);
+        // wakes the accept loop up to see it stopped
+        let _ = match &self.address {
+            Address::Tcp { host, port } => TcpStream::connect((&host[..], *port)).map(drop),
+            #[cfg(unix)]
+            Address::Unix(path) => UnixStream::connect(path).map(drop),
+            #[cfg(not(unix))]
+            Address::Unix(_) => Ok(()),
+        };
+        self.disconnect_all();
+        #[cfg(unix)]
+        if let Address::Unix(path) = &self.address {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        lock(&self.state)
+    }
+
+    fn serve_connection(&self, socket: Socket) {
+        let Ok(registered) = socket.try_clone() else {
+            return;
+        };
+        let mut session = {
+            let mut state = self.lock();
+            state.next_client_id += 1;
+            Session::new(state.next_client_id - 1)
+        };
+        lock(&self.connections).insert(session.id, registered);
+
+        let parser = Parser::new(socket);
+        while let Ok(Data::Array(args)) = parser.next() {
+            let args: Vec<Bytes> = args.into_iter().filter_map(Data::into_bytes).collect();
+            if args.is_empty() {
+                continue;
+            }
+            let bytes = match self.respond(&mut session, &args) {
+                Scripted::Reply(reply) => {
+                    let mut bytes = Vec::new();
+                    for frame in session.pending.drain(..).chain([reply]) {
+                        frame.encode(session.protocol, &mut bytes);
+                    }
+                    bytes
+                }
+                Scripted::Raw(bytes) => bytes,
+                Scripted::Disconnect => break,
+            };
+            if self.write(session.id, &bytes).is_err() || session.quit {
+                break;
+            }
+        }
+        self.lock().subscriptions.remove(&session.id);
+        if let Some(socket) = lock(&self.connections).remove(&session.id) {
+            socket.shutdown();
+        }
+    }
+
+    /// Writes to a connection, through the registered socket so that
+    /// messages published by other connections are not interleaved.
+    fn write(&self, id: i64, bytes: &[u8]) -> io::Result<()> {
+        match lock(&self.connections).get_mut(&id) {
+            Some(socket) => socket.write_all(bytes),
+            None => Err(io::ErrorKind::NotConnected.into()),
+        }
+    }
+
+    /// Runs a command unless a script or handler answers it.
+    fn respond(&self, session: &mut Session, args: &[Bytes]) -> Scripted {
+        let name = String::from_utf8_lossy(&args[0]).to_ascii_uppercase();
+        let mut state = self.lock();
+        let text: Vec<_> = args
+            .iter()
+            .map(|arg| String::from_utf8_lossy(arg))
+            .collect();
+        state.commands.push(text.join(" "));
+
+        if let Some(scripted) = state.scripts.get_mut(&name).and_then(VecDeque::pop_front) {
+            return scripted;
+        }
+        if let Some(handler) = state.handlers.get(&name).cloned() {
+            drop(state);
+            return handler(args);
+        }
+        let reply = state.execute(session, &name, args);
+
+        let outbox = std::mem::take(&mut state.outbox);
+        drop(state);
+        for (id, message) in outbox {
+            // the subscriber may be gone already
+            let _ = self.write(id, &message);
+        }
+        Scripted::Reply(reply)
+    }
+}
+
+impl State {
+    fn execute(&mut self, session: &mut Session, name: &str, args: &[Bytes]) -> Data {
+        self.dispatch(session, name, args)
+            .unwrap_or_else(Data::Error)
+    }
+
+    fn dispatch(
+        &mut self,
+        session: &mut Session,
+        name: &str,
+        args: &[Bytes],
+    ) -> Result<Data, String> {
+        if let Err(err) = commands::check_arity(name, args) {
+            session.aborted |= session.queued.is_some();
+            return Err(err);
+        }
+        if self.password.is_some()
+            && !session.authenticated
+            && !matches!(name, "AUTH" | "HELLO" | "QUIT")
+        {
+            return Err(String::from("NOAUTH Authentication required."));
+        }
+        let subscribed = self
+            .subscriptions
+            .get(&session.id)
+            .is_some_and(|subscriptions| subscriptions.count() > 0);
+        if subscribed
+            && session.protocol == ProtocolVersion::Resp2
+            && !matches!(
+                name,
+                "SUBSCRIBE"
+                    | "PSUBSCRIBE"
+                    | "UNSUBSCRIBE"
+                    | "PUNSUBSCRIBE"
+                    | "PING"
+                    | "QUIT"
+                    | "RESET"
+            )
+        {
+            return Err(format!(
+                "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+                name.to_ascii_lowercase()
+            ));
+        }
+        if let Some(queued) = &mut session.queued {
+            if !matches!(
+                name,
+                "DISCARD" | "EXEC" | "MULTI" | "QUIT" | "RESET" | "WATCH"
+            ) {
+                queued.push(args.to_vec());
+                return Ok(Data::from("QUEUED"));
+            }
+        }
+
+        let ok = || Ok(Data::from("OK"));
+        match name {
+            "PING" => Ok(match args.get(1) {
+                Some(message) => Data::from(message.clone()),
+                None => Data::from("PONG"),
+            }),
+            "ECHO" => Ok(Data::from(args[1].clone())),
+            "HELLO" => self.hello(session, args),
+            "AUTH" => match &args[1..] {
+                [password] => self.auth(session, None, password),
+                [username, password] => self.auth(session, Some(username), password),
+                _ => Err(commands::syntax_error()),
+            },
+            "CLIENT" => match (&args[1].to_ascii_uppercase()[..], &args[2..]) {
+                (b"SETNAME", [name]) => {
+                    session.name = Some(name.clone());
+                    ok()
+                }
+                (b"GETNAME", []) => Ok(session.name.clone().map_or(Data::Null, Data::from)),
+                (b"ID", []) => Ok(Data::Integer(session.id)),
+                _ => Err(format!(
+                    "ERR unknown subcommand '{}'. Try CLIENT HELP.",
+                    String::from_utf8_lossy(&args[1])
+                )),
+            },
+            "SELECT" => {
+                session.db = usize::try_from(commands::int(&args[1])?)
+                    .ok()
+                    .filter(|db| *db < DATABASES)
+                    .ok_or("ERR DB index is out of range")?;
+                ok()
+            }
+            "QUIT" => {
+                session.quit = true;
+                ok()
+            }
+            "RESET" => {
+                self.subscriptions.remove(&session.id);
+                *session = Session::new(session.id);
+                Ok(Data::from("RESET"))
+            }
+            "MULTI" if session.queued.is_some() => {
+                Err(String::from("ERR MULTI calls can not be nested"))
+            }
+            "MULTI" => {
+                session.queued = Some(Vec::new());
+                ok()
+            }
+            "DISCARD" => match session.queued.take() {
+                Some(_) => {
+                    session.aborted = false;
+                    session.watched.clear();
+                    ok()
+                }
+                None => Err(String::from("ERR DISCARD without MULTI")),
+            },
+            "EXEC" => {
+                let queued = session.queued.take().ok_or("ERR EXEC without MULTI")?;
+                let watched = std::mem::take(&mut session.watched);
+                if std::mem::take(&mut session.aborted) {
+                    return Err(String::from(
+                        "EXECABORT Transaction discarded because of previous errors.",
+                    ));
+                }
+                if watched
+                    .into_iter()
+                    .any(|(db, key, value)| self.value(db, &key) != value)
+                {
+                    return Ok(Data::Null);
+                }
+                Ok(Data::Array(
+                    queued
+                        .iter()
+                        .map(|args| {
+                            let name = String::from_utf8_lossy(&args[0]).to_ascii_uppercase();
+                            self.execute(session, &name, args)
+                        })
+                        .collect(),
+                ))
+            }
+            "WATCH" if session.queued.is_some() => {
+                Err(String::from("ERR WATCH inside MULTI is not allowed"))
+            }
+            // a key set again to the same value does not count as changed
+            "WATCH" => {
+                for key in &args[1..] {
+                    let value = self.value(session.db, key);
+                    session.watched.push((session.db, key.clone(), value));
+                }
+                ok()
+            }
+            "UNWATCH" => {
+                session.watched.clear();
+                ok()
+            }
+            "SUBSCRIBE" | "PSUBSCRIBE" => Ok(self.subscribe(session, name, &args[1..])),
+            "UNSUBSCRIBE" | "PUNSUBSCRIBE" => Ok(self.unsubscribe(session, name, &args[1..])),
+            "PUBLISH" => Ok(Data::Integer(self.publish(&args[1], &args[2]))),
+            "FLUSHALL" => {
+                self.dbs.iter_mut().for_each(Db::clear);
+                ok()
+            }
+            "INFO" => {
+                let keyspace: String = self
+                    .dbs
+                    .iter_mut()
+                    .map(Db::len)
+                    .enumerate()
+                    .filter(|(_, keys)| *keys > 0)
+                    .map(|(i, keys)| format!("db{i}:keys={keys},expires=0,avg_ttl=0\r\n"))
+                    .collect();
+                Ok(Data::from(format!(
+                    "# Server\r\nredis_version:7.2.0\r\nredis_mode:standalone\r\n\r\n# Keyspace\r\n{keyspace}"
+                )))
+            }
+            "TIME" => {
+                let now = SystemTime::now()
+                    .duration_since(UNIX_EPOCH)
+                    .unwrap_or_default();
+                Ok(Data::Array(vec![
+                    Data::from(now.as_secs().to_string()),
+                    Data::from(now.subsec_micros().to_string()),
+                ]))
+            }
+            "CONFIG" => match &args[1].to_ascii_uppercase()[..] {
+                b"GET" => Ok(Data::Map(Vec::new())),
+                b"SET" => ok(),
+                _ => Err(format!(
+                    "ERR unknown subcommand '{}'. Try CONFIG HELP.",
+                    String::from_utf8_lossy(&args[1])
+                )),
+            },
+            _ => commands::run(&mut self.dbs[session.db], session.protocol, name, args),
+        }
+    }
+
+    /// `SUBSCRIBE` and `PSUBSCRIBE`, confirming each channel or pattern
+    /// with its own frame.
+    fn subscribe(&mut self, session: &mut Session, name: &str, names: &[Bytes]) -> Data {
+        let subscriptions = self.subscriptions.entry(session.id).or_default();
+        subscriptions.protocol = session.protocol;
+        let kind = name.to_ascii_lowercase();
+        let mut confirmations = Vec::new();
+        for channel in names {
+            match name {
+                "SUBSCRIBE" => subscriptions.channels.insert(channel.clone()),
+                _ => subscriptions.patterns.insert(channel.clone()),
+            };
+            confirmations.push(push(
+                session.protocol,
+                vec![
+                    Data::from(kind.as_str()),
+                    Data::from(channel.clone()),
+                    Data::Integer(subscriptions.count()),
+                ],
+            ));
+        }
+        session.frames(confirmations)
+    }
+
+    /// `UNSUBSCRIBE` and `PUNSUBSCRIBE`, from every channel or pattern when
+    /// none is given.
+    fn unsubscribe(&mut self, session: &mut Session, name: &str, names: &[Bytes]) -> Data {
+        let subscriptions = self.subscriptions.entry(session.id).or_default();
+        let subscribed = match name {
+            "UNSUBSCRIBE" => &mut subscriptions.channels,
+            _ => &mut subscriptions.patterns,
+        };
+        let names = match names {
+            [] => std::mem::take(subscribed).into_iter().collect(),
+            names => names.to_vec(),
+        };
+        for channel in &names {
+            subscribed.remove(channel);
+        }
+
+        let kind = name.to_ascii_lowercase();
+        let count = subscriptions.count();
+        let mut confirmations: Vec<Data> = names
+            .into_iter()
+            .map(|channel| {
+                push(
+                    session.protocol,
+                    vec![
+                        Data::from(kind.as_str()),
+                        Data::from(channel),
+                        Data::Integer(count),
+                    ],
+                )
+            })
+            .collect();
+        if confirmations.is_empty() {
+            confirmations.push(push(
+                session.protocol,
+                vec![Data::from(kind.as_str()), Data::Null, Data::Integer(count)],
+            ));
+        }
+        session.frames(confirmations)
+    }
+
+    /// Queues the message for every matching subscription, returning how
+    /// many receive it.
+    fn publish(&mut self, channel: &[u8], message: &[u8]) -> i64 {
+        let mut receivers = 0;
+        for (id, subscriptions) in &self.subscriptions {
+            let protocol = subscriptions.protocol;
+            if subscriptions.channels.contains(channel) {
+                let frame = push(
+                    protocol,
+                    vec![
+                        Data::from("message"),
+                        Data::from(channel.to_vec()),
+                        Data::from(message.to_vec()),
+                    ],
+                );
+                self.outbox.push((*id, frame.to_resp(protocol)));
+                receivers += 1;
+            }
+            for pattern in &subscriptions.patterns {
+                if glob_match(pattern, channel) {
+                    let frame = push(
+                        protocol,
+                        vec![
+                            Data::from("pmessage"),
+                            Data::from(pattern.clone()),
+                            Data::from(channel.to_vec()),
+                            Data::from(message.to_vec()),
+                        ],
+                    );
+                    self.outbox.push((*id, frame.to_resp(protocol)));
+                    receivers += 1;
+                }
+            }
+        }
+        receivers
+    }
+
+    fn value(&mut self, db: usize, key: &[u8]) -> Option<Value> {
+        self.dbs[db].get(key).map(|entry| entry.value.clone())
+    }
+
+    /// `HELLO [protover [AUTH username password] [SETNAME clientname]]`,
+    /// switching the connection to the requested protocol.
+    fn hello(&mut self, session: &mut Session, args: &[Bytes]) -> Result<Data, String> {
+        let mut protocol = session.protocol;
+        let mut options = args[1..].iter();
+        if let Some(version) = options.next() {
+            protocol = match &version[..] {
+                b"2" => ProtocolVersion::Resp2,
+                b"3" => ProtocolVersion::Resp3,
+                _ => return Err(String::from("NOPROTO unsupported protocol version")),
+            };
+        }
+        while let Some(option) = options.next() {
+            match &option.to_ascii_uppercase()[..] {
+                b"AUTH" => {
+                    let (Some(username), Some(password)) = (options.next(), options.next()) else {
+                        return Err(commands::syntax_error());
+                    };
+                    self.auth(session, Some(username), password)?;
+                }
+                b"SETNAME" => {
+                    session.name = Some(options.next().ok_or_else(commands::syntax_error)?.clone())
+                }
+                _ => return Err(commands::syntax_error()),
+            }
+        }
+        if self.password.is_some() && !session.authenticated {
+            return Err(String::from(
+                "NOAUTH HELLO must be called with the client already authenticated",
+            ));
+        }
+
+        session.protocol = protocol;
+        let version = match protocol {
+            ProtocolVersion::Resp2 => 2,
+            ProtocolVersion::Resp3 => 3,
+        };
+        Ok(Data::Map(vec![
+            (Data::from("server"), Data::from("redis")),
+            (Data::from("version"), Data::from("7.2.0")),
+            (Data::from("proto"), Data::Integer(version)),
+            (Data::from("id"), Data::Integer(session.id)),
+            (Data::from("mode"), Data::from("standalone")),
+            (Data::from("role"), Data::from("master")),
+            (Data::from("modules"), Data::Array(Vec::new())),
+        ]))
+    }
+
+    fn auth(
+        &self,
+        session: &mut Session,
+        username: Option<&Bytes>,
+        password: &[u8],
+    ) -> Result<Data, String> {
+        let accepted = match (&self.password, username) {
+            (Some(expected), username) => {
+                username.is_none_or(|username| username == b"default")
+                    && password == expected.as_bytes()
+            }
+            // without a password the default user takes any
+            (None, Some(username)) => username == b"default",
+            (None, None) => {
+                return Err(String::from(
+                    "ERR AUTH <password> called without any password configured for the default user. Are you sure your configuration is correct?",
+                ))
+            }
+        };
+        if !accepted {
+            return Err(String::from(
+                "WRONGPASS invalid username-password pair or user is disabled.",
+            ));
+        }
+        session.authenticated = true;
+        Ok(Data::from("OK"))
+    }
+}
+
+/// An out of band frame, a push over RESP3 and an array over RESP2.
+fn push(protocol: ProtocolVersion, items: Vec<Data>) -> Data {
+    match protocol {
+        ProtocolVersion::Resp3 => Data::Push(items),
+        ProtocolVersion::Resp2 => Data::Array(items),
+    }
+}
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex
+        .lock()
+        .unwrap_or_else(|poisoned| poisoned.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, time::Duration};
+
+    use super::*;
+    use crate::client::{
+        commands::{Limit, ScanOptions, ScoreBound},
+        errors::RedashError,
+        reconnect::ReconnectPolicy,
+        transaction::TransactionOutcome,
+        Client,
+    };
+
+    fn reply_code(err: RedashError) -> String {
+        String::from(err.reply().expect("an error reply").code())
+    }
+
+    #[test]
+    fn test_strings_and_keys() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+
+        client.set("a", "1").unwrap();
+        client.mset(&[("b", "x"), ("c", "y")]).unwrap();
+        assert_eq!(client.incr_by("a", 41).unwrap(), 42);
+        assert_eq!(client.incr_by_float("f", 0.5).unwrap(), 0.5);
+        assert_eq!(client.append("b", "yz").unwrap(), 3);
+        let values: Vec<Option<String>> = client.mget(&["a", "b", "missing"]).unwrap();
+        assert_eq!(
+            values,
+            vec![Some(String::from("42")), Some(String::from("xyz")), None]
+        );
+
+        assert_eq!(client.exists(&["a", "a", "missing"]).unwrap(), 2);
+        assert_eq!(client.key_type("b").unwrap(), "string");
+        client.rename("c", "d").unwrap();
+        let mut keys: Vec<String> = client.keys("[a-d]").unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["a", "b", "d"]);
+        assert_eq!(client.del(&["a", "missing"]).unwrap(), 1);
+
+        let mut cursor = 0;
+        let mut scanned = Vec::new();
+        loop {
+            let options = ScanOptions {
+                count: Some(1),
+                ..ScanOptions::default()
+            };
+            let (next, keys): (u64, Vec<String>) = client.scan(cursor, &options).unwrap();
+            scanned.extend(keys);
+            if next == 0 {
+                break;
+            }
+            cursor = next;
+        }
+        assert_eq!(scanned, vec!["b", "d", "f"]);
+        assert_eq!(client.dbsize().unwrap(), 3);
+    }
+
+    #[test]
+    fn test_expiration() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+
+        client.set("k", "v").unwrap();
+        assert_eq!(client.ttl("k").unwrap(), -1);
+        assert!(client.expire("k", 100, None).unwrap());
+        assert_eq!(client.ttl("k").unwrap(), 100);
+        assert!(client.persist("k").unwrap());
+        assert_eq!(client.ttl("missing").unwrap(), -2);
+
+        client.pexpire("k", 20, None).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(client.get::<Option<String>, _>("k").unwrap(), None);
+        assert_eq!(client.exists(&["k"]).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_hashes_and_lists() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+
+        assert_eq!(client.hset("h", &[("f", "1"), ("g", "2")]).unwrap(), 2);
+        assert_eq!(client.hincr_by("h", "f", 9).unwrap(), 10);
+        let hash: HashMap<String, String> = client.hgetall("h").unwrap();
+        assert_eq!(hash["f"], "10");
+        assert_eq!(client.hdel("h", &["f", "g"]).unwrap(), 2);
+        assert_eq!(client.exists(&["h"]).unwrap(), 0);
+
+        client.rpush("l", &["b", "c", "b"]).unwrap();
+        client.lpush("l", &["a"]).unwrap();
+        let items: Vec<String> = client.lrange("l", 0, -1).unwrap();
+        assert_eq!(items, vec!["a", "b", "c", "b"]);
+        assert_eq!(client.lrem("l", -1, "b").unwrap(), 1);
+        let popped: Vec<String> = client.lpop("l", Some(2)).unwrap();
+        assert_eq!(popped, vec!["a", "b"]);
+        let (list, item): (String, String) = client.blpop(&["empty", "l"], 0.0).unwrap();
+        assert_eq!((&list[..], &item[..]), ("l", "c"));
+        assert_eq!(client.llen("l").unwrap(), 0);
+    }
+
+    #[test]
+    fn test_sets_and_sorted_sets() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+
+        client.sadd("s", &["a", "b", "c"]).unwrap();
+        client.sadd("t", &["b", "c", "d"]).unwrap();
+        let common: Vec<String> = client.sinter(&["s", "t"]).unwrap();
+        assert_eq!(common, vec!["b", "c"]);
+        let only: Vec<String> = client.sdiff(&["s", "t"]).unwrap();
+        assert_eq!(only, vec!["a"]);
+        assert!(client.sismember("t", "d").unwrap());
+
+        client
+            .zadd("z", &[(2.0, "b"), (1.0, "a"), (3.5, "c")])
+            .unwrap();
+        assert_eq!(client.zincr_by("z", 2.0, "a").unwrap(), 3.0);
+        assert_eq!(client.zrank("z", "a").unwrap(), Some(1));
+        assert_eq!(client.zscore("z", "missing").unwrap(), None);
+        assert_eq!(
+            client
+                .zcount("z", ScoreBound::Exclusive(2.0), ScoreBound::PosInfinity)
+                .unwrap(),
+            2
+        );
+        let members: Vec<(String, f64)> = client
+            .zrange_by_score_with_scores(
+                "z",
+                ScoreBound::NegInfinity,
+                ScoreBound::PosInfinity,
+                Some(Limit {
+                    offset: 1,
+                    count: 5,
+                }),
+            )
+            .unwrap();
+        assert_eq!(
+            members,
+            vec![(String::from("a"), 3.0), (String::from("c"), 3.5)]
+        );
+    }
+
+    #[test]
+    fn test_error_replies() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+
+        client.set("k", "v").unwrap();
+        let err = client.lpush("k", &["x"]).unwrap_err();
+        assert!(err.reply().unwrap().is_wrong_type());
+        assert_eq!(reply_code(client.incr("k").unwrap_err()), "ERR");
+        assert!(client
+            .send_command("GET")
+            .unwrap_err()
+            .to_string()
+            .contains("wrong number of arguments for 'get'"));
+        assert!(client
+            .send_command("NOPE")
+            .unwrap_err()
+            .to_string()
+            .contains("unknown command 'NOPE'"));
+    }
+
+    #[test]
+    fn test_handshake_with_resp3() {
+        let server = FakeServer::start().unwrap();
+        server.require_password("secret");
+        let client = Client::with_options(ConnectionOptions {
+            protocol: ProtocolVersion::Resp3,
+            password: Some(String::from("secret")),
+            client_name: Some(String::from("app")),
+            database: Some(2),
+            ..server.options()
+        });
+        client.connect().unwrap();
+
+        client.zadd("z", &[(1.5, "a")]).unwrap();
+        assert_eq!(
+            client.send_command("ZSCORE z a").unwrap(),
+            Data::Double(1.5)
+        );
+        let members: Vec<(String, f64)> = client.zrange_with_scores("z", 0, -1).unwrap();
+        assert_eq!(members, vec![(String::from("a"), 1.5)]);
+        assert_eq!(
+            client.send_command("CLIENT GETNAME").unwrap(),
+            Data::from("app")
+        );
+        assert_eq!(
+            server.commands()[..2],
+            ["HELLO 3 AUTH default secret SETNAME app", "SELECT 2"]
+        );
+
+        // the key lives in database 2 only
+        let other = Client::with_options(ConnectionOptions {
+            password: Some(String::from("secret")),
+            ..server.options()
+        });
+        other.connect().unwrap();
+        assert_eq!(other.dbsize().unwrap(), 0);
+
+        let wrong = Client::with_options(ConnectionOptions {
+            password: Some(String::from("wrong")),
+            ..server.options()
+        });
+        assert!(matches!(wrong.connect(), Err(RedashError::AuthFailed(_))));
+        let anonymous = server.connect().unwrap();
+        assert!(matches!(
+            anonymous.ping(),
+            Err(RedashError::AuthRequired(_))
+        ));
+    }
+
+    #[test]
+    fn test_transactions() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+
+        let result = client
+            .transaction()
+            .args(&["INCR", "n"])
+            .args(&["LPUSH", "n", "x"])
+            .args(&["INCR", "n"])
+            .execute()
+            .unwrap();
+        assert!(result.queued.iter().all(|queued| queued.is_ok()));
+        match result.outcome {
+            TransactionOutcome::Committed(replies) => {
+                assert_eq!(replies[0].as_ref().unwrap(), &Data::Integer(1));
+                assert!(replies[1]
+                    .as_ref()
+                    .unwrap_err()
+                    .reply()
+                    .unwrap()
+                    .is_wrong_type());
+                assert_eq!(replies[2].as_ref().unwrap(), &Data::Integer(2));
+            }
+            outcome => panic!("unexpected outcome: {outcome:?}"),
+        }
+
+        let result = client
+            .transaction()
+            .args(&["INCR", "n"])
+            .args(&["NOPE"])
+            .execute()
+            .unwrap();
+        match result.outcome {
+            TransactionOutcome::Aborted(err) => assert!(err.reply().unwrap().is_exec_abort()),
+            outcome => panic!("unexpected outcome: {outcome:?}"),
+        }
+        assert_eq!(client.get::<i64, _>("n").unwrap(), 2);
+    }
+
+    #[test]
+    fn test_scripted_replies_and_injected_errors() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        client.set("k", "v").unwrap();
+
+        server.inject_error("get", "BUSY script running");
+        server.script("GET", Scripted::Reply(Data::from("scripted")));
+        let err = client.get::<String, _>("k").unwrap_err();
+        assert!(err.reply().unwrap().is_busy());
+        assert_eq!(client.get::<String, _>("k").unwrap(), "scripted");
+        assert_eq!(client.get::<String, _>("k").unwrap(), "v");
+
+        server.handle("TIME", |_| {
+            Scripted::Reply(Data::Array(vec![Data::from("1"), Data::from("2")]))
+        });
+        assert_eq!(client.time().unwrap(), (1, 2));
+        assert_eq!(client.time().unwrap(), (1, 2));
+
+        server.script("PING", Scripted::Raw(b"+PONG\r\n".to_vec()));
+        client.ping().unwrap();
+    }
+
+    #[test]
+    fn test_client_reconnects_after_disconnect() {
+        let server = FakeServer::start().unwrap();
+        let client = Client::with_options(ConnectionOptions {
+            reconnect: ReconnectPolicy::disabled(),
+            ..server.options()
+        });
+        client.connect().unwrap();
+        client.set("k", "v").unwrap();
+
+        server.script("GET", Scripted::Disconnect);
+        assert!(client
+            .get::<String, _>("k")
+            .unwrap_err()
+            .is_connection_error());
+        client.connect().unwrap();
+        assert_eq!(client.get::<String, _>("k").unwrap(), "v");
+
+        server.disconnect_all();
+        assert!(client.ping().unwrap_err().is_connection_error());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket() {
+        let path = std::env::temp_dir().join(format!("redash-fake-{}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server = FakeServer::start_unix(&path).unwrap();
+        let client = server.connect().unwrap();
+
+        client.sadd("s", &["a"]).unwrap();
+        assert_eq!(client.scard("s").unwrap(), 1);
+        drop(server);
+        assert!(!path.exists());
+    }
+}
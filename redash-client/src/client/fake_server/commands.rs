@@ -0,0 +1,843 @@
+//! Data commands, run against one database.
+
+use std::{
+    collections::BTreeSet,
+    str::from_utf8,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use super::store::{glob_match, Bytes, Db, Entry, Value};
+use crate::client::{parser::Data, ProtocolVersion};
+
+/// Known commands with their arity as in `COMMAND INFO`: the exact number
+/// of arguments including the name, or its negation for a minimum.
+const COMMANDS: &[(&str, i64)] = &[
+    // connection and server
+    ("AUTH", -2),
+    ("CLIENT", -2),
+    ("CONFIG", -2),
+    ("DBSIZE", 1),
+    ("DISCARD", 1),
+    ("ECHO", 2),
+    ("EXEC", 1),
+    ("FLUSHALL", -1),
+    ("FLUSHDB", -1),
+    ("HELLO", -1),
+    ("INFO", -1),
+    ("MULTI", 1),
+    ("PING", -1),
+    ("QUIT", -1),
+    ("RESET", 1),
+    ("SELECT", 2),
+    ("TIME", 1),
+    ("UNWATCH", 1),
+    ("WATCH", -2),
+    // pub/sub
+    ("PSUBSCRIBE", -2),
+    ("PUBLISH", 3),
+    ("PUNSUBSCRIBE", -1),
+    ("SUBSCRIBE", -2),
+    ("UNSUBSCRIBE", -1),
+    // keys
+    ("DEL", -2),
+    ("EXISTS", -2),
+    ("EXPIRE", -3),
+    ("KEYS", 2),
+    ("PERSIST", 2),
+    ("PEXPIRE", -3),
+    ("PTTL", 2),
+    ("RENAME", 3),
+    ("SCAN", -2),
+    ("TTL", 2),
+    ("TYPE", 2),
+    // strings
+    ("APPEND", 3),
+    ("DECR", 2),
+    ("DECRBY", 3),
+    ("GET", 2),
+    ("GETDEL", 2),
+    ("INCR", 2),
+    ("INCRBY", 3),
+    ("INCRBYFLOAT", 3),
+    ("MGET", -2),
+    ("MSET", -3),
+    ("SET", -3),
+    ("STRLEN", 2),
+    // hashes
+    ("HDEL", -3),
+    ("HEXISTS", 3),
+    ("HGET", 3),
+    ("HGETALL", 2),
+    ("HINCRBY", 4),
+    ("HKEYS", 2),
+    ("HLEN", 2),
+    ("HMGET", -3),
+    ("HSET", -4),
+    ("HSETNX", 4),
+    ("HVALS", 2),
+    // lists
+    ("BLPOP", -3),
+    ("LINDEX", 3),
+    ("LLEN", 2),
+    ("LPOP", -2),
+    ("LPUSH", -3),
+    ("LRANGE", 4),
+    ("LREM", 4),
+    ("LTRIM", 4),
+    ("RPOP", -2),
+    ("RPUSH", -3),
+    // sets
+    ("SADD", -3),
+    ("SCARD", 2),
+    ("SDIFF", -2),
+    ("SINTER", -2),
+    ("SISMEMBER", 3),
+    ("SMEMBERS", 2),
+    ("SREM", -3),
+    ("SUNION", -2),
+    // sorted sets
+    ("ZADD", -4),
+    ("ZCARD", 2),
+    ("ZCOUNT", 4),
+    ("ZINCRBY", 4),
+    ("ZRANGE", -4),
+    ("ZRANGEBYSCORE", -4),
+    ("ZRANK", 3),
+    ("ZREM", -3),
+    ("ZSCORE", 3),
+];
+
+/// Checks that the command is known and has enough arguments.
+pub(super) fn check_arity(name: &str, args: &[Bytes]) -> Result<(), String> {
+    let Some(&(_, arity)) = COMMANDS.iter().find(|(known, _)| *known == name) else {
+        return Err(format!(
+            "ERR unknown command '{}', with args beginning with: ",
+            String::from_utf8_lossy(&args[0])
+        ));
+    };
+    let len = args.len() as i64;
+    if (arity >= 0 && len != arity) || len < -arity {
+        return Err(wrong_arity(name));
+    }
+    Ok(())
+}
+
+/// Runs a data command on `db`, `name` being the upper case command name
+/// that was checked with [`check_arity`].
+pub(super) fn run(
+    db: &mut Db,
+    protocol: ProtocolVersion,
+    name: &str,
+    args: &[Bytes],
+) -> Result<Data, String> {
+    let key: &[u8] = args.get(1).map_or(&[], |key| &key[..]);
+    match name {
+        "DBSIZE" => Ok(Data::Integer(db.len() as i64)),
+        "FLUSHDB" => {
+            db.clear();
+            Ok(ok())
+        }
+
+        // keys
+        "DEL" => Ok(count(
+            args[1..].iter().filter(|key| db.remove(key).is_some()),
+        )),
+        "EXISTS" => Ok(count(args[1..].iter().filter(|key| db.get(key).is_some()))),
+        "EXPIRE" => expire(db, args, 1000),
+        "PEXPIRE" => expire(db, args, 1),
+        "PERSIST" => Ok(Data::Integer(match db.get(key) {
+            Some(entry) => i64::from(entry.expires_at.take().is_some()),
+            None => 0,
+        })),
+        "TTL" => Ok(ttl(db, key, 1000)),
+        "PTTL" => Ok(ttl(db, key, 1)),
+        "TYPE" => Ok(Data::from(
+            db.get(key).map_or("none", |entry| entry.value.type_name()),
+        )),
+        "KEYS" => Ok(strings(
+            db.keys().iter().filter(|key| glob_match(&args[1], key)),
+        )),
+        "RENAME" => {
+            let entry = db.remove(key).ok_or("ERR no such key")?;
+            db.insert(&args[2], entry);
+            Ok(ok())
+        }
+        "SCAN" => scan(db, args),
+
+        // strings
+        "GET" => Ok(db.string(key)?.map_or(Data::Null, |value| bulk(value))),
+        "GETDEL" => {
+            let value = db.string(key)?.map_or(Data::Null, |value| bulk(value));
+            db.remove(key);
+            Ok(value)
+        }
+        "SET" => set(db, args),
+        "MGET" => Ok(Data::Array(
+            args[1..]
+                .iter()
+                .map(|key| match db.string(key) {
+                    Ok(Some(value)) => bulk(value),
+                    _ => Data::Null,
+                })
+                .collect(),
+        )),
+        "MSET" => {
+            if args.len().is_multiple_of(2) {
+                return Err(wrong_arity(name));
+            }
+            for pair in args[1..].chunks(2) {
+                db.set(&pair[0], Value::String(pair[1].clone()));
+            }
+            Ok(ok())
+        }
+        "INCR" => incr_by(db, key, 1),
+        "DECR" => incr_by(db, key, -1),
+        "INCRBY" => incr_by(db, key, int(&args[2])?),
+        "DECRBY" => incr_by(db, key, int(&args[2])?.checked_neg().ok_or_else(overflow)?),
+        "INCRBYFLOAT" => {
+            let current = match db.string(key)? {
+                Some(value) => float(value)?,
+                None => 0.0,
+            };
+            let next = current + float(&args[2])?;
+            if !next.is_finite() {
+                return Err(String::from("ERR increment would produce NaN or Infinity"));
+            }
+            *db.string_or_insert(key)? = next.to_string().into_bytes();
+            Ok(Data::from(next.to_string()))
+        }
+        "APPEND" => {
+            let value = db.string_or_insert(key)?;
+            value.extend_from_slice(&args[2]);
+            Ok(Data::Integer(value.len() as i64))
+        }
+        "STRLEN" => Ok(Data::Integer(
+            db.string(key)?.map_or(0, |value| value.len()) as i64,
+        )),
+
+        // hashes
+        "HGET" => Ok(db
+            .hash(key)?
+            .and_then(|hash| hash.get(&args[2]))
+            .map_or(Data::Null, |value| bulk(value))),
+        "HSET" => {
+            if !args.len().is_multiple_of(2) {
+                return Err(wrong_arity(name));
+            }
+            let hash = db.hash_or_insert(key)?;
+            Ok(count(args[2..].chunks(2).filter(|pair| {
+                hash.insert(pair[0].clone(), pair[1].clone()).is_none()
+            })))
+        }
+        "HSETNX" => {
+            let hash = db.hash_or_insert(key)?;
+            let added = !hash.contains_key(&args[2]);
+            if added {
+                hash.insert(args[2].clone(), args[3].clone());
+            }
+            Ok(Data::Integer(i64::from(added)))
+        }
+        "HMGET" => {
+            let hash = db.hash(key)?;
+            let hash = hash.as_deref();
+            Ok(Data::Array(
+                args[2..]
+                    .iter()
+                    .map(|field| {
+                        hash.and_then(|hash| hash.get(field))
+                            .map_or(Data::Null, |value| bulk(value))
+                    })
+                    .collect(),
+            ))
+        }
+        "HGETALL" => Ok(Data::Map(
+            db.hash(key)?
+                .map(|hash| {
+                    hash.iter()
+                        .map(|(field, value)| (bulk(field), bulk(value)))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        )),
+        "HDEL" => {
+            let Some(hash) = db.hash(key)? else {
+                return Ok(Data::Integer(0));
+            };
+            let removed = count(
+                args[2..]
+                    .iter()
+                    .filter(|field| hash.remove(*field).is_some()),
+            );
+            db.remove_if_empty(key);
+            Ok(removed)
+        }
+        "HEXISTS" => Ok(Data::Integer(i64::from(
+            db.hash(key)?
+                .is_some_and(|hash| hash.contains_key(&args[2])),
+        ))),
+        "HINCRBY" => {
+            let increment = int(&args[3])?;
+            let hash = db.hash_or_insert(key)?;
+            let current = match hash.get(&args[2]) {
+                Some(value) => {
+                    int(value).map_err(|_| String::from("ERR hash value is not an integer"))?
+                }
+                None => 0,
+            };
+            let next = current.checked_add(increment).ok_or_else(overflow)?;
+            hash.insert(args[2].clone(), next.to_string().into_bytes());
+            Ok(Data::Integer(next))
+        }
+        "HLEN" => Ok(Data::Integer(
+            db.hash(key)?.map_or(0, |hash| hash.len()) as i64
+        )),
+        "HKEYS" => Ok(strings(db.hash(key)?.iter().flat_map(|hash| hash.keys()))),
+        "HVALS" => Ok(strings(db.hash(key)?.iter().flat_map(|hash| hash.values()))),
+
+        // lists
+        "LPUSH" | "RPUSH" => {
+            let list = db.list_or_insert(key)?;
+            for value in &args[2..] {
+                if name == "LPUSH" {
+                    list.push_front(value.clone());
+                } else {
+                    list.push_back(value.clone());
+                }
+            }
+            Ok(Data::Integer(list.len() as i64))
+        }
+        "LPOP" | "RPOP" => {
+            let count = match args.get(2) {
+                Some(count) => Some(
+                    usize::try_from(int(count)?)
+                        .map_err(|_| String::from("ERR value is out of range, must be positive"))?,
+                ),
+                None => None,
+            };
+            let Some(list) = db.list(key)? else {
+                return Ok(Data::Null);
+            };
+            let mut pop = || match name {
+                "LPOP" => list.pop_front(),
+                _ => list.pop_back(),
+            };
+            let reply = match count {
+                Some(count) => {
+                    Data::Array((0..count).map_while(|_| pop()).map(Data::from).collect())
+                }
+                None => pop().map_or(Data::Null, Data::from),
+            };
+            db.remove_if_empty(key);
+            Ok(reply)
+        }
+        "BLPOP" => {
+            float(&args[args.len() - 1])
+                .map_err(|_| String::from("ERR timeout is not a float or out of range"))?;
+            // never blocks: the first list with an element answers, or none
+            for key in &args[1..args.len() - 1] {
+                if let Some(value) = db.list(key)?.and_then(|list| list.pop_front()) {
+                    db.remove_if_empty(key);
+                    return Ok(Data::Array(vec![bulk(key), Data::from(value)]));
+                }
+            }
+            Ok(Data::Null)
+        }
+        "LRANGE" => {
+            let (start, stop) = (int(&args[2])?, int(&args[3])?);
+            let Some(list) = db.list(key)? else {
+                return Ok(Data::Array(Vec::new()));
+            };
+            Ok(strings(match range(start, stop, list.len()) {
+                Some((start, stop)) => list.range(start..=stop),
+                None => list.range(0..0),
+            }))
+        }
+        "LINDEX" => {
+            let index = int(&args[2])?;
+            let Some(list) = db.list(key)? else {
+                return Ok(Data::Null);
+            };
+            let index = if index < 0 {
+                index + list.len() as i64
+            } else {
+                index
+            };
+            Ok(usize::try_from(index)
+                .ok()
+                .and_then(|index| list.get(index))
+                .map_or(Data::Null, |value| bulk(value)))
+        }
+        "LLEN" => Ok(Data::Integer(
+            db.list(key)?.map_or(0, |list| list.len()) as i64
+        )),
+        "LREM" => {
+            let limit = int(&args[2])?;
+            let Some(list) = db.list(key)? else {
+                return Ok(Data::Integer(0));
+            };
+            let element = &args[3];
+            let mut matches: Vec<usize> =
+                (0..list.len()).filter(|&i| list[i] == *element).collect();
+            if limit < 0 {
+                matches.reverse();
+            }
+            if limit != 0 {
+                matches.truncate(limit.unsigned_abs().try_into().unwrap_or(usize::MAX));
+            }
+            matches.sort_unstable();
+            for &index in matches.iter().rev() {
+                list.remove(index);
+            }
+            db.remove_if_empty(key);
+            Ok(Data::Integer(matches.len() as i64))
+        }
+        "LTRIM" => {
+            let (start, stop) = (int(&args[2])?, int(&args[3])?);
+            if let Some(list) = db.list(key)? {
+                match range(start, stop, list.len()) {
+                    Some((start, stop)) => {
+                        list.truncate(stop + 1);
+                        list.drain(..start);
+                    }
+                    None => list.clear(),
+                }
+                db.remove_if_empty(key);
+            }
+            Ok(ok())
+        }
+
+        // sets
+        "SADD" => {
+            let set = db.set_or_insert(key)?;
+            Ok(count(
+                args[2..]
+                    .iter()
+                    .filter(|member| set.insert(member.to_vec())),
+            ))
+        }
+        "SREM" => {
+            let Some(set) = db.set_members(key)? else {
+                return Ok(Data::Integer(0));
+            };
+            let removed = count(args[2..].iter().filter(|member| set.remove(*member)));
+            db.remove_if_empty(key);
+            Ok(removed)
+        }
+        "SMEMBERS" => Ok(Data::Set(
+            members(db, key)?.into_iter().map(Data::from).collect(),
+        )),
+        "SISMEMBER" => Ok(Data::Integer(i64::from(
+            db.set_members(key)?
+                .is_some_and(|set| set.contains(&args[2])),
+        ))),
+        "SCARD" => Ok(Data::Integer(
+            db.set_members(key)?.map_or(0, |set| set.len()) as i64,
+        )),
+        "SINTER" | "SUNION" | "SDIFF" => {
+            let mut result = members(db, key)?;
+            for key in &args[2..] {
+                let other = members(db, key)?;
+                match name {
+                    "SINTER" => result.retain(|member| other.contains(member)),
+                    "SUNION" => result.extend(other),
+                    _ => result.retain(|member| !other.contains(member)),
+                }
+            }
+            Ok(Data::Set(result.into_iter().map(Data::from).collect()))
+        }
+
+        // sorted sets
+        "ZADD" => {
+            if !args.len().is_multiple_of(2) {
+                return Err(syntax_error());
+            }
+            let members = args[2..]
+                .chunks(2)
+                .map(|pair| Ok((float(&pair[0])?, pair[1].clone())))
+                .collect::<Result<Vec<_>, String>>()?;
+            let zset = db.sorted_set_or_insert(key)?;
+            Ok(count(members.into_iter().filter(|(score, member)| {
+                zset.insert(member.clone(), *score).is_none()
+            })))
+        }
+        "ZREM" => {
+            let Some(zset) = db.sorted_set(key)? else {
+                return Ok(Data::Integer(0));
+            };
+            let removed = count(
+                args[2..]
+                    .iter()
+                    .filter(|member| zset.remove(*member).is_some()),
+            );
+            db.remove_if_empty(key);
+            Ok(removed)
+        }
+        "ZSCORE" => Ok(db
+            .sorted_set(key)?
+            .and_then(|zset| zset.get(&args[2]).copied())
+            .map_or(Data::Null, Data::Double)),
+        "ZINCRBY" => {
+            let increment = float(&args[2])?;
+            let score = db
+                .sorted_set_or_insert(key)?
+                .entry(args[3].clone())
+                .or_insert(0.0);
+            *score += increment;
+            if score.is_nan() {
+                return Err(String::from("ERR resulting score is not a number (NaN)"));
+            }
+            Ok(Data::Double(*score))
+        }
+        "ZCARD" => Ok(Data::Integer(
+            db.sorted_set(key)?.map_or(0, |zset| zset.len()) as i64,
+        )),
+        "ZCOUNT" => {
+            let (min, max) = (score_bound(&args[2])?, score_bound(&args[3])?);
+            Ok(count(
+                ranked(db, key)?
+                    .iter()
+                    .filter(|(_, score)| in_bounds(*score, min, max)),
+            ))
+        }
+        "ZRANK" => Ok(ranked(db, key)?
+            .iter()
+            .position(|(member, _)| *member == args[2])
+            .map_or(Data::Null, |rank| Data::Integer(rank as i64))),
+        "ZRANGE" => {
+            let (start, stop) = (int(&args[2])?, int(&args[3])?);
+            let with_scores = match &args[4..] {
+                [] => false,
+                [option] if option.eq_ignore_ascii_case(b"WITHSCORES") => true,
+                _ => return Err(syntax_error()),
+            };
+            let members = ranked(db, key)?;
+            let members: &[_] = match range(start, stop, members.len()) {
+                Some((start, stop)) => &members[start..=stop],
+                None => &[],
+            };
+            Ok(scored(members, with_scores, protocol))
+        }
+        "ZRANGEBYSCORE" => {
+            let (min, max) = (score_bound(&args[2])?, score_bound(&args[3])?);
+            let mut with_scores = false;
+            let (mut offset, mut limit) = (0, usize::MAX);
+            let mut options = args[4..].iter();
+            while let Some(option) = options.next() {
+                match &option.to_ascii_uppercase()[..] {
+                    b"WITHSCORES" => with_scores = true,
+                    b"LIMIT" => {
+                        let (Some(start), Some(count)) = (options.next(), options.next()) else {
+                            return Err(syntax_error());
+                        };
+                        // a negative offset returns nothing, a negative count everything
+                        offset = usize::try_from(int(start)?).unwrap_or(usize::MAX);
+                        limit = usize::try_from(int(count)?).unwrap_or(usize::MAX);
+                    }
+                    _ => return Err(syntax_error()),
+                }
+            }
+            let members: Vec<_> = ranked(db, key)?
+                .into_iter()
+                .filter(|(_, score)| in_bounds(*score, min, max))
+                .skip(offset)
+                .take(limit)
+                .collect();
+            Ok(scored(&members, with_scores, protocol))
+        }
+
+        _ => Err(format!(
+            "ERR unknown command '{}', with args beginning with: ",
+            String::from_utf8_lossy(&args[0])
+        )),
+    }
+}
+
+fn ok() -> Data {
+    Data::from("OK")
+}
+
+fn bulk(value: &[u8]) -> Data {
+    Data::from(value.to_vec())
+}
+
+fn strings<'a>(values: impl IntoIterator<Item = &'a Bytes>) -> Data {
+    Data::Array(values.into_iter().map(|value| bulk(value)).collect())
+}
+
+fn count<T>(items: impl Iterator<Item = T>) -> Data {
+    Data::Integer(items.count() as i64)
+}
+
+fn wrong_arity(name: &str) -> String {
+    format!(
+        "ERR wrong number of arguments for '{}' command",
+        name.to_ascii_lowercase()
+    )
+}
+
+pub(super) fn syntax_error() -> String {
+    String::from("ERR syntax error")
+}
+
+fn overflow() -> String {
+    String::from("ERR increment or decrement would overflow")
+}
+
+pub(super) fn int(arg: &[u8]) -> Result<i64, String> {
+    from_utf8(arg)
+        .ok()
+        .and_then(|arg| arg.parse().ok())
+        .ok_or_else(|| String::from("ERR value is not an integer or out of range"))
+}
+
+fn float(arg: &[u8]) -> Result<f64, String> {
+    from_utf8(arg)
+        .ok()
+        .and_then(|arg| arg.parse::<f64>().ok())
+        .filter(|value| !value.is_nan())
+        .ok_or_else(|| String::from("ERR value is not a valid float"))
+}
+
+/// Resolves inclusive `start..=stop` indexes of `len` elements, negative
+/// ones counting from the end, or `None` for an empty range.
+fn range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+fn unix_millis() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+/// Instant `millis` from now, `None` if it is too far to represent.
+fn deadline(millis: i64) -> Option<Instant> {
+    Instant::now().checked_add(Duration::from_millis(millis.max(0) as u64))
+}
+
+fn incr_by(db: &mut Db, key: &[u8], increment: i64) -> Result<Data, String> {
+    let current = match db.string(key)? {
+        Some(value) => int(value)?,
+        None => 0,
+    };
+    let next = current.checked_add(increment).ok_or_else(overflow)?;
+    *db.string_or_insert(key)? = next.to_string().into_bytes();
+    Ok(Data::Integer(next))
+}
+
+fn set(db: &mut Db, args: &[Bytes]) -> Result<Data, String> {
+    let (key, value) = (&args[1], &args[2]);
+    let (mut condition, mut get, mut keep_ttl) = (None, false, false);
+    let mut expires_at = None;
+    let mut options = args[3..].iter();
+    while let Some(option) = options.next() {
+        let option = option.to_ascii_uppercase();
+        match &option[..] {
+            b"NX" | b"XX" if condition.is_none() => condition = Some(option.clone()),
+            b"GET" => get = true,
+            b"KEEPTTL" if expires_at.is_none() => keep_ttl = true,
+            b"EX" | b"PX" | b"EXAT" | b"PXAT" if expires_at.is_none() && !keep_ttl => {
+                let amount = int(options.next().ok_or_else(syntax_error)?)?;
+                let millis = match &option[..] {
+                    b"EX" | b"EXAT" => amount.checked_mul(1000),
+                    _ => Some(amount),
+                }
+                .filter(|millis| *millis > 0)
+                .ok_or_else(|| String::from("ERR invalid expire time in 'set' command"))?;
+                let millis = match &option[..] {
+                    b"EXAT" | b"PXAT" => millis - unix_millis(),
+                    _ => millis,
+                };
+                expires_at = Some(deadline(millis));
+            }
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    let previous = db.get(key).map(|entry| entry.expires_at);
+    let old_value = match db.string(key) {
+        Ok(value) => value.cloned(),
+        Err(err) if get => return Err(err),
+        Err(_) => None,
+    };
+    let write = match condition.as_deref() {
+        Some(b"NX") => previous.is_none(),
+        Some(_) => previous.is_some(),
+        None => true,
+    };
+    if write {
+        let expires_at = match keep_ttl {
+            true => previous.flatten(),
+            false => expires_at.flatten(),
+        };
+        let value = Value::String(value.clone());
+        db.insert(key, Entry { value, expires_at });
+    }
+    Ok(match (get, write) {
+        (true, _) => old_value.map_or(Data::Null, Data::from),
+        (false, true) => ok(),
+        (false, false) => Data::Null,
+    })
+}
+
+fn expire(db: &mut Db, args: &[Bytes], unit: i64) -> Result<Data, String> {
+    let millis = int(&args[2])?
+        .checked_mul(unit)
+        .ok_or_else(|| String::from("ERR invalid expire time in 'expire' command"))?;
+    let condition = match &args[3..] {
+        [] => None,
+        [condition] => Some(condition.to_ascii_uppercase()),
+        _ => return Err(syntax_error()),
+    };
+    let Some(entry) = db.get(&args[1]) else {
+        return Ok(Data::Integer(0));
+    };
+    let (current, new) = (entry.expires_at, deadline(millis));
+    // no expiration counts as an infinite time to live
+    let apply = match condition.as_deref() {
+        None => true,
+        Some(b"NX") => current.is_none(),
+        Some(b"XX") => current.is_some(),
+        Some(b"GT") => current.is_some_and(|current| new.is_none_or(|new| new > current)),
+        Some(b"LT") => new.is_some_and(|new| current.is_none_or(|current| new < current)),
+        Some(_) => return Err(syntax_error()),
+    };
+    if apply {
+        entry.expires_at = new;
+    }
+    Ok(Data::Integer(i64::from(apply)))
+}
+
+fn ttl(db: &mut Db, key: &[u8], unit: u128) -> Data {
+    Data::Integer(match db.get(key) {
+        None => -2,
+        Some(Entry {
+            expires_at: None, ..
+        }) => -1,
+        Some(Entry {
+            expires_at: Some(expires_at),
+            ..
+        }) => {
+            let millis = expires_at
+                .saturating_duration_since(Instant::now())
+                .as_millis();
+            ((millis + unit / 2) / unit) as i64
+        }
+    })
+}
+
+/// Pages through keys in order, the cursor being the index of the next key.
+fn scan(db: &mut Db, args: &[Bytes]) -> Result<Data, String> {
+    let cursor = usize::try_from(int(&args[1])?).map_err(|_| String::from("ERR invalid cursor"))?;
+    let (mut pattern, mut count, mut key_type) = (None, 10, None);
+    let mut options = args[2..].iter();
+    while let Some(option) = options.next() {
+        let value = options.next().ok_or_else(syntax_error)?;
+        match &option.to_ascii_uppercase()[..] {
+            b"MATCH" => pattern = Some(value),
+            b"COUNT" => {
+                count = usize::try_from(int(value)?)
+                    .ok()
+                    .filter(|count| *count > 0)
+                    .ok_or_else(syntax_error)?
+            }
+            b"TYPE" => key_type = Some(value.to_ascii_lowercase()),
+            _ => return Err(syntax_error()),
+        }
+    }
+
+    let keys = db.keys();
+    let end = cursor.saturating_add(count).min(keys.len());
+    let page = keys.get(cursor..end).unwrap_or_default();
+    let mut found = Vec::new();
+    for key in page {
+        let matches_type = match &key_type {
+            Some(key_type) => db
+                .get(key)
+                .is_some_and(|entry| entry.value.type_name().as_bytes() == key_type),
+            None => true,
+        };
+        if matches_type && pattern.is_none_or(|pattern| glob_match(pattern, key)) {
+            found.push(bulk(key));
+        }
+    }
+    let next = if end >= keys.len() { 0 } else { end };
+    Ok(Data::Array(vec![
+        Data::from(next.to_string()),
+        Data::Array(found),
+    ]))
+}
+
+fn members(db: &mut Db, key: &[u8]) -> Result<BTreeSet<Bytes>, String> {
+    Ok(db.set_members(key)?.cloned().unwrap_or_default())
+}
+
+/// Members of a sorted set by ascending score, ties ordered by member.
+fn ranked(db: &mut Db, key: &[u8]) -> Result<Vec<(Bytes, f64)>, String> {
+    let mut members: Vec<_> = db
+        .sorted_set(key)?
+        .map(|zset| {
+            zset.iter()
+                .map(|(member, score)| (member.clone(), *score))
+                .collect()
+        })
+        .unwrap_or_default();
+    members.sort_by(|(a, a_score), (b, b_score)| a_score.total_cmp(b_score).then(a.cmp(b)));
+    Ok(members)
+}
+
+/// Members of a range, followed by their scores with `WITHSCORES`: flat in
+/// RESP2 and as pairs in RESP3.
+fn scored(members: &[(Bytes, f64)], with_scores: bool, protocol: ProtocolVersion) -> Data {
+    Data::Array(match (with_scores, protocol) {
+        (false, _) => members.iter().map(|(member, _)| bulk(member)).collect(),
+        (true, ProtocolVersion::Resp2) => members
+            .iter()
+            .flat_map(|(member, score)| [bulk(member), Data::Double(*score)])
+            .collect(),
+        (true, ProtocolVersion::Resp3) => members
+            .iter()
+            .map(|(member, score)| Data::Array(vec![bulk(member), Data::Double(*score)]))
+            .collect(),
+    })
+}
+
+/// Parses a score bound, `(` making it exclusive.
+fn score_bound(arg: &[u8]) -> Result<(f64, bool), String> {
+    let (arg, exclusive) = match arg.strip_prefix(b"(") {
+        Some(arg) => (arg, true),
+        None => (arg, false),
+    };
+    float(arg)
+        .map(|score| (score, exclusive))
+        .map_err(|_| String::from("ERR min or max is not a float"))
+}
+
+fn in_bounds(
+    score: f64,
+    (min, min_exclusive): (f64, bool),
+    (max, max_exclusive): (f64, bool),
+) -> bool {
+    let above = if min_exclusive {
+        score > min
+    } else {
+        score >= min
+    };
+    let below = if max_exclusive {
+        score < max
+    } else {
+        score <= max
+    };
+    above && below
+}
@@ -0,0 +1,196 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    time::Instant,
+};
+
+pub(super) type Bytes = Vec<u8>;
+
+/// Value stored at a key.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) enum Value {
+    String(Bytes),
+    Hash(BTreeMap<Bytes, Bytes>),
+    List(VecDeque<Bytes>),
+    Set(BTreeSet<Bytes>),
+    SortedSet(BTreeMap<Bytes, f64>),
+}
+
+impl Value {
+    pub(super) fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::Hash(_) => "hash",
+            Value::List(_) => "list",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match self {
+            Value::String(_) => false,
+            Value::Hash(hash) => hash.is_empty(),
+            Value::List(list) => list.is_empty(),
+            Value::Set(set) => set.is_empty(),
+            Value::SortedSet(zset) => zset.is_empty(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct Entry {
+    pub(super) value: Value,
+    pub(super) expires_at: Option<Instant>,
+}
+
+pub(super) fn wrong_type() -> String {
+    String::from("WRONGTYPE Operation against a key holding the wrong kind of value")
+}
+
+/// Accessors to a value of one type, failing with `WRONGTYPE` on another.
+macro_rules! typed_access {
+    ($get:ident, $get_or_insert:ident, $variant:ident, $ty:ty) => {
+        pub(super) fn $get(&mut self, key: &[u8]) -> Result<Option<&mut $ty>, String> {
+            match self.get(key) {
+                None => Ok(None),
+                Some(Entry {
+                    value: Value::$variant(value),
+                    ..
+                }) => Ok(Some(value)),
+                Some(_) => Err(wrong_type()),
+            }
+        }
+
+        pub(super) fn $get_or_insert(&mut self, key: &[u8]) -> Result<&mut $ty, String> {
+            if self.get(key).is_none() {
+                self.set(key, Value::$variant(Default::default()));
+            }
+            self.$get(key).map(|value| value.expect("inserted above"))
+        }
+    };
+}
+
+/// One numbered database. Keys are kept sorted so that listings are stable.
+#[derive(Debug, Default)]
+pub(super) struct Db {
+    entries: BTreeMap<Bytes, Entry>,
+}
+
+impl Db {
+    /// The entry at a key, dropping it first if it expired.
+    pub(super) fn get(&mut self, key: &[u8]) -> Option<&mut Entry> {
+        let now = Instant::now();
+        if let Some(Entry {
+            expires_at: Some(expires_at),
+            ..
+        }) = self.entries.get(key)
+        {
+            if *expires_at <= now {
+                self.entries.remove(key);
+            }
+        }
+        self.entries.get_mut(key)
+    }
+
+    /// Stores a value without expiration, replacing any previous one.
+    pub(super) fn set(&mut self, key: &[u8], value: Value) {
+        let entry = Entry {
+            value,
+            expires_at: None,
+        };
+        self.insert(key, entry);
+    }
+
+    pub(super) fn insert(&mut self, key: &[u8], entry: Entry) {
+        self.entries.insert(key.to_vec(), entry);
+    }
+
+    pub(super) fn remove(&mut self, key: &[u8]) -> Option<Entry> {
+        self.get(key)?;
+        self.entries.remove(key)
+    }
+
+    /// Deletes a collection left empty, as the server does.
+    pub(super) fn remove_if_empty(&mut self, key: &[u8]) {
+        if self.get(key).is_some_and(|entry| entry.value.is_empty()) {
+            self.entries.remove(key);
+        }
+    }
+
+    /// Live keys in order.
+    pub(super) fn keys(&mut self) -> Vec<Bytes> {
+        let now = Instant::now();
+        self.entries
+            .retain(|_, entry| entry.expires_at.is_none_or(|expires_at| expires_at > now));
+        self.entries.keys().cloned().collect()
+    }
+
+    pub(super) fn len(&mut self) -> usize {
+        self.keys().len()
+    }
+
+    pub(super) fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    typed_access!(string, string_or_insert, String, Bytes);
+    typed_access!(hash, hash_or_insert, Hash, BTreeMap<Bytes, Bytes>);
+    typed_access!(list, list_or_insert, List, VecDeque<Bytes>);
+    typed_access!(set_members, set_or_insert, Set, BTreeSet<Bytes>);
+    typed_access!(sorted_set, sorted_set_or_insert, SortedSet, BTreeMap<Bytes, f64>);
+}
+
+/// Glob-style matching of `KEYS` and `SCAN`: `*`, `?`, `[abc]`, `[a-z]`,
+/// `[^a]` and `\` to escape.
+pub(super) fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some((b'*', rest)) => (0..=text.len()).any(|skip| glob_match(rest, &text[skip..])),
+        Some((b'?', rest)) => !text.is_empty() && glob_match(rest, &text[1..]),
+        Some((b'[', rest)) => {
+            let Some((&ch, text_rest)) = text.split_first() else {
+                return false;
+            };
+            let (negate, rest) = match rest.split_first() {
+                Some((b'^', rest)) => (true, rest),
+                _ => (false, rest),
+            };
+            let Some(end) = rest.iter().position(|&byte| byte == b']') else {
+                return false;
+            };
+            let class = &rest[..end];
+            let mut matched = false;
+            let mut i = 0;
+            while i < class.len() {
+                if i + 2 < class.len() && class[i + 1] == b'-' {
+                    matched |= (class[i]..=class[i + 2]).contains(&ch);
+                    i += 3;
+                } else {
+                    matched |= class[i] == ch;
+                    i += 1;
+                }
+            }
+            matched != negate && glob_match(&rest[end + 1..], text_rest)
+        }
+        Some((b'\\', [escaped, rest @ ..])) => {
+            text.first() == Some(escaped) && glob_match(rest, &text[1..])
+        }
+        Some((byte, rest)) => text.first() == Some(byte) && glob_match(rest, &text[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match(b"user:*", b"user:1"));
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(glob_match(b"h[a-e]llo", b"hello"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"a\\*", b"a*"));
+        assert!(!glob_match(b"a\\*", b"ab"));
+        assert!(!glob_match(b"*:1", b"user:12"));
+    }
+}
@@ -0,0 +1,360 @@
+use std::{
+    collections::HashMap,
+    hash::{BuildHasher, Hash},
+    str::{from_utf8, FromStr},
+};
+
+use bytes::Bytes;
+
+use super::{errors::RedashError, parser::Data, Client};
+
+/// Conversion of a reply into a Rust type, used by [`Client::query`].
+///
+/// Strings holding numbers convert to numbers, nulls convert to `None` and
+/// to empty collections, and maps convert from both RESP3 maps and the flat
+/// key/value arrays RESP2 servers send.
+///
+/// # Examples
+/// ```no_run
+/// # use std::collections::HashMap;
+/// # use redash_client::client::Client;
+/// # let client = Client::new("127.0.0.1", 6379);
+/// let visits: i64 = client.query("INCR visits").unwrap();
+/// let user: HashMap<String, String> = client.query("HGETALL user:1").unwrap();
+/// let name: Option<String> = client.query("GET name").unwrap();
+/// ```
+pub trait FromData: Sized {
+    fn from_data(data: Data) -> Result<Self, RedashError>;
+
+    /// Converts a whole string reply into a `Vec<Self>`, so that `Vec<u8>`
+    /// reads binary strings while other vectors read arrays. The bytes are
+    /// handed back when the type does not support it.
+    #[doc(hidden)]
+    fn from_byte_vec(bytes: Vec<u8>) -> Result<Vec<Self>, Vec<u8>> {
+        Err(bytes)
+    }
+}
+
+impl Client {
+    /// Sends a `redis-cli` style command line and converts its reply.
+    pub fn query<T: FromData>(&self, command: &str) -> Result<T, RedashError> {
+        T::from_data(self.send_command(command)?)
+    }
+
+    /// Sends already split arguments and converts the reply.
+    pub fn query_args<T: FromData, A: AsRef<[u8]>>(&self, args: &[A]) -> Result<T, RedashError> {
+        T::from_data(self.send_args(args)?)
+    }
+}
+
+fn type_error<T>(expected: &'static str, data: &Data) -> Result<T, RedashError> {
+    Err(RedashError::TypeError {
+        expected,
+        actual: describe(data),
+    })
+}
+
+/// Reply type, with the value of short scalars and the length of
+/// aggregates, e.g. `integer 300` or `array of 3 items`.
+fn describe(data: &Data) -> String {
+    match data {
+        Data::String(_) | Data::Verbatim { .. } => match data.as_str() {
+            Some(text) if text.len() <= 32 => format!("{} {text:?}", data.kind()),
+            Some(_) => String::from(data.kind()),
+            None => format!("{} (not UTF-8)", data.kind()),
+        },
+        Data::Integer(i) => format!("integer {i}"),
+        Data::Double(d) => format!("double {d}"),
+        Data::Boolean(b) => format!("boolean {b}"),
+        Data::BigNumber(n) => format!("big number {n}"),
+        Data::Array(items) | Data::Set(items) | Data::Push(items) => {
+            format!("{} of {} items", data.kind(), items.len())
+        }
+        Data::Map(entries) => format!("map of {} entries", entries.len()),
+        data => String::from(data.kind()),
+    }
+}
+
+/// Drops attributes and turns error replies nested in aggregates into errors.
+fn unwrap(data: Data) -> Result<Data, RedashError> {
+    match data {
+        Data::Attribute { data, .. } => unwrap(*data),
+        Data::Error(err) => Err(RedashError::from_reply(&err)),
+        data => Ok(data),
+    }
+}
+
+impl FromData for Data {
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        Ok(data)
+    }
+}
+
+/// Accepts any reply that is not an error, e.g. the `OK` of `SET`.
+impl FromData for () {
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        unwrap(data).map(|_| ())
+    }
+}
+
+fn integer<T: TryFrom<i64> + FromStr>(
+    data: Data,
+    expected: &'static str,
+) -> Result<T, RedashError> {
+    let data = unwrap(data)?;
+    let value = match &data {
+        Data::Integer(i) => T::try_from(*i).ok(),
+        Data::BigNumber(n) => n.parse().ok(),
+        data => data.as_str().and_then(|text| text.parse().ok()),
+    };
+    value.map_or_else(|| type_error(expected, &data), Ok)
+}
+
+macro_rules! from_integer {
+    ($($t:ty),*) => {$(
+        impl FromData for $t {
+            fn from_data(data: Data) -> Result<Self, RedashError> {
+                integer(data, stringify!($t))
+            }
+        }
+    )*};
+}
+
+from_integer!(i8, i16, i32, i64, i128, isize, u16, u32, u64, u128, usize);
+
+impl FromData for u8 {
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        integer(data, "u8")
+    }
+
+    fn from_byte_vec(bytes: Vec<u8>) -> Result<Vec<Self>, Vec<u8>> {
+        Ok(bytes)
+    }
+}
+
+macro_rules! from_float {
+    ($($t:ty),*) => {$(
+        impl FromData for $t {
+            fn from_data(data: Data) -> Result<Self, RedashError> {
+                let data = unwrap(data)?;
+                let value = match &data {
+                    Data::Double(d) => Some(*d as $t),
+                    Data::Integer(i) => Some(*i as $t),
+                    Data::BigNumber(n) => n.parse().ok(),
+                    data => data.as_str().and_then(|text| text.parse().ok()),
+                };
+                value.map_or_else(|| type_error(stringify!($t), &data), Ok)
+            }
+        }
+    )*};
+}
+
+from_float!(f32, f64);
+
+impl FromData for bool {
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        let data = unwrap(data)?;
+        match &data {
+            Data::Boolean(b) => Ok(*b),
+            Data::Integer(0) => Ok(false),
+            Data::Integer(1) => Ok(true),
+            data => match data.as_str() {
+                Some("0") => Ok(false),
+                Some("1") => Ok(true),
+                _ => type_error("bool", data),
+            },
+        }
+    }
+}
+
+impl FromData for String {
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        match unwrap(data)? {
+            Data::String(bytes) => match from_utf8(&bytes) {
+                Ok(s) => Ok(String::from(s)),
+                Err(_) => type_error("String", &Data::String(bytes)),
+            },
+            Data::Verbatim { text, .. } => Ok(text),
+            Data::Integer(i) => Ok(i.to_string()),
+            Data::Double(d) => Ok(d.to_string()),
+            Data::BigNumber(n) => Ok(n),
+            data => type_error("String", &data),
+        }
+    }
+}
+
+/// Reads a string reply without copying it out of the reply buffer.
+impl FromData for Bytes {
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        match unwrap(data)? {
+            Data::String(bytes) => Ok(bytes),
+            Data::Verbatim { text, .. } => Ok(Bytes::from(text)),
+            data => type_error("Bytes", &data),
+        }
+    }
+}
+
+impl<T: FromData> FromData for Option<T> {
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        match unwrap(data)? {
+            Data::Null => Ok(None),
+            data => T::from_data(data).map(Some),
+        }
+    }
+}
+
+impl<T: FromData> FromData for Vec<T> {
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        match unwrap(data)? {
+            Data::Array(items) | Data::Set(items) | Data::Push(items) => {
+                items.into_iter().map(T::from_data).collect()
+            }
+            // each entry converts like a two item array, e.g. into tuples
+            Data::Map(entries) => entries
+                .into_iter()
+                .map(|(key, value)| T::from_data(Data::Array(vec![key, value])))
+                .collect(),
+            Data::Null => Ok(Vec::new()),
+            Data::String(bytes) => T::from_byte_vec(Vec::from(bytes))
+                .or_else(|bytes| type_error("Vec", &Data::from(bytes))),
+            data => type_error("Vec", &data),
+        }
+    }
+}
+
+impl<K, V, S> FromData for HashMap<K, V, S>
+where
+    K: FromData + Eq + Hash,
+    V: FromData,
+    S: BuildHasher + Default,
+{
+    fn from_data(data: Data) -> Result<Self, RedashError> {
+        match unwrap(data)? {
+            Data::Map(entries) => entries
+                .into_iter()
+                .map(|(key, value)| Ok((K::from_data(key)?, V::from_data(value)?)))
+                .collect(),
+            // RESP2 sends maps as flat arrays of keys and values
+            Data::Array(items) if items.len() % 2 == 0 => {
+                let mut items = items.into_iter();
+                let mut map = HashMap::with_capacity_and_hasher(items.len() / 2, S::default());
+                while let (Some(key), Some(value)) = (items.next(), items.next()) {
+                    map.insert(K::from_data(key)?, V::from_data(value)?);
+                }
+                Ok(map)
+            }
+            Data::Null => Ok(HashMap::default()),
+            data => type_error("HashMap", &data),
+        }
+    }
+}
+
+macro_rules! from_tuple {
+    ($len:literal; $($name:ident),+) => {
+        impl<$($name: FromData),+> FromData for ($($name,)+) {
+            fn from_data(data: Data) -> Result<Self, RedashError> {
+                match unwrap(data)? {
+                    Data::Array(items) | Data::Set(items) | Data::Push(items) if items.len() == $len => {
+                        let mut items = items.into_iter();
+                        Ok(($($name::from_data(items.next().expect("length checked"))?,)+))
+                    }
+                    data => type_error(concat!("tuple of ", $len, " items"), &data),
+                }
+            }
+        }
+    };
+}
+
+from_tuple!(1; A);
+from_tuple!(2; A, B);
+from_tuple!(3; A, B, C);
+from_tuple!(4; A, B, C, D);
+from_tuple!(5; A, B, C, D, E);
+from_tuple!(6; A, B, C, D, E, F);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error_message<T: FromData + std::fmt::Debug>(data: Data) -> String {
+        T::from_data(data).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn test_scalars() {
+        assert_eq!(i64::from_data(Data::Integer(-3)).unwrap(), -3);
+        assert_eq!(u16::from_data(Data::from("42")).unwrap(), 42);
+        assert_eq!(f64::from_data(Data::from("1.5")).unwrap(), 1.5);
+        assert_eq!(f64::from_data(Data::from("inf")).unwrap(), f64::INFINITY);
+        assert_eq!(f32::from_data(Data::Double(0.25)).unwrap(), 0.25);
+        assert!(bool::from_data(Data::Integer(1)).unwrap());
+        assert!(!bool::from_data(Data::Boolean(false)).unwrap());
+        assert_eq!(String::from_data(Data::Integer(7)).unwrap(), "7");
+        assert_eq!(
+            Vec::<u8>::from_data(Data::from(vec![0, 255])).unwrap(),
+            vec![0, 255]
+        );
+        assert_eq!(
+            Bytes::from_data(Data::from("raw")).unwrap(),
+            Bytes::from_static(b"raw")
+        );
+        assert_eq!(Option::<String>::from_data(Data::Null).unwrap(), None);
+        <()>::from_data(Data::from("OK")).unwrap();
+    }
+
+    #[test]
+    fn test_collections() {
+        let reply = Data::Array(vec![
+            Data::from("a"),
+            Data::from("1"),
+            Data::from("b"),
+            Data::from("2"),
+        ]);
+        let map: HashMap<String, i64> = HashMap::from_data(reply.clone()).unwrap();
+        assert_eq!(
+            map,
+            HashMap::from([(String::from("a"), 1), (String::from("b"), 2)])
+        );
+        let values: Vec<String> = Vec::from_data(reply).unwrap();
+        assert_eq!(values, vec!["a", "1", "b", "2"]);
+
+        let reply = Data::Map(vec![(Data::from("a"), Data::Double(1.5))]);
+        let map: HashMap<String, f64> = HashMap::from_data(reply.clone()).unwrap();
+        assert_eq!(map["a"], 1.5);
+        let pairs: Vec<(String, f64)> = Vec::from_data(reply).unwrap();
+        assert_eq!(pairs, vec![(String::from("a"), 1.5)]);
+
+        let reply = Data::Array(vec![Data::from("k"), Data::Null, Data::Integer(3)]);
+        let tuple: (String, Option<String>, u8) = FromData::from_data(reply).unwrap();
+        assert_eq!(tuple, (String::from("k"), None, 3));
+    }
+
+    #[test]
+    fn test_errors_name_expected_and_actual_types() {
+        assert_eq!(
+            error_message::<u8>(Data::Integer(300)),
+            "cannot convert integer 300 reply to u8"
+        );
+        assert_eq!(
+            error_message::<i64>(Data::from("abc")),
+            "cannot convert string \"abc\" reply to i64"
+        );
+        assert_eq!(
+            error_message::<(i64, i64)>(Data::Array(vec![Data::Integer(1)])),
+            "cannot convert array of 1 items reply to tuple of 2 items"
+        );
+        assert_eq!(
+            error_message::<String>(Data::from(vec![0xff])),
+            "cannot convert string (not UTF-8) reply to String"
+        );
+        assert_eq!(
+            error_message::<HashMap<String, String>>(Data::Integer(1)),
+            "cannot convert integer 1 reply to HashMap"
+        );
+        // error replies nested in aggregates keep their code
+        let err =
+            Vec::<i64>::from_data(Data::Array(vec![Data::Error(String::from("WRONGTYPE no"))]))
+                .unwrap_err();
+        assert!(err.reply().unwrap().is_wrong_type());
+    }
+}
@@ -1,177 +1,623 @@
 use std::{
-    cell::RefCell,
+    cell::{RefCell, RefMut},
     fmt::Display,
-    io::{BufReader, Read},
+    io::{ErrorKind, Read},
     str::from_utf8,
 };
 
+use bytes::{Bytes, BytesMut};
+
 use super::errors::RedashError;
 
+/// Reads replies from a blocking source, buffering what is read ahead of
+/// the current reply for the next one.
 pub struct Parser<T: Read> {
-    source: RefCell<BufReader<T>>,
+    source: RefCell<T>,
+    buffer: RefCell<BytesMut>,
+    // read into once allocated, then appended to the buffer
+    chunk: RefCell<Vec<u8>>,
+    decoder: RefCell<FrameDecoder>,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Data {
-    String(String),
+    /// Simple or bulk string, kept as raw bytes since bulk strings are binary safe.
+    /// Parsed payloads are slices of the buffer the reply was read into.
+    String(Bytes),
     Integer(i64),
-    Array(Vec<Box<Data>>),
+    Array(Vec<Data>),
     Null,
+    /// Error reply nested inside an aggregate, e.g. a failed command in `EXEC`,
+    /// or a whole reply returned by [`FrameDecoder::decode`].
+    Error(String),
+    // RESP3 types
+    Double(f64),
+    Boolean(bool),
+    BigNumber(String),
+    Verbatim {
+        format: String,
+        text: String,
+    },
+    Map(Vec<(Data, Data)>),
+    Set(Vec<Data>),
+    Push(Vec<Data>),
+    /// A reply decorated with out-of-band attributes (`|`).
+    Attribute {
+        attributes: Vec<(Data, Data)>,
+        data: Box<Data>,
+    },
 }
 
 impl Display for Data {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match &self {
-            &Data::Array(arr) => {
+        match self {
+            Data::Array(arr) | Data::Set(arr) | Data::Push(arr) => {
                 for i in arr {
-                    write!(f, "{i}\n")?
+                    writeln!(f, "{i}")?
+                }
+            }
+            Data::Map(entries) => {
+                for (key, value) in entries {
+                    writeln!(f, "{key} => {value}")?
                 }
             }
-            &Data::Integer(i) => write!(f, "{i}")?,
-            &Data::Null => write!(f, "null")?,
-            &Data::String(s) => write!(f, "{s}")?,
+            Data::Integer(i) => write!(f, "{i}")?,
+            Data::Null => write!(f, "null")?,
+            Data::Error(err) => write!(f, "(error) {err}")?,
+            Data::String(s) => match from_utf8(s) {
+                Ok(s_str) => write!(f, "{s_str}")?,
+                Err(_) => write!(f, "\"{}\"", s.escape_ascii())?,
+            },
+            Data::Double(d) => write!(f, "{d}")?,
+            Data::Boolean(b) => write!(f, "{b}")?,
+            Data::BigNumber(n) => write!(f, "{n}")?,
+            Data::Verbatim { text, .. } => write!(f, "{text}")?,
+            Data::Attribute { data, .. } => write!(f, "{data}")?,
         };
         Ok(())
     }
 }
 
-impl<T: Read> Parser<T> {
-    pub fn new(source: T) -> Self {
-        let buf_reader = BufReader::new(source);
-        return Parser {
-            source: RefCell::new(buf_reader),
-        };
+impl Data {
+    /// Raw bytes of a string reply.
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            Data::String(s) => Some(s),
+            Data::Verbatim { text, .. } => Some(text.as_bytes()),
+            _ => None,
+        }
     }
 
-    pub fn next(&self) -> Result<Data, RedashError> {
-        let type_indicator = self.type_indicator()?;
-
-        let data = match type_indicator {
-            b'-' => {
-                if let Err(err) = self.data_error() {
-                    Err(err)
-                } else {
-                    Ok(Data::Null)
-                }
-            }
-            b'+' => self.data_simple_string(),
-            b':' => self.data_integer(),
-            b'$' => self.data_bulk_string(),
-            b'*' => self.data_array(),
-            u => Err(RedashError::ServerError(
-                String::from("invalid_server_data_type"),
-                u,
-            )),
-        }?;
+    /// Text of a string reply, `None` if it is not a string or not valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Data::String(s) => from_utf8(s).ok(),
+            Data::Verbatim { text, .. } => Some(text),
+            _ => None,
+        }
+    }
+
+    /// Name of the reply type, as used in conversion errors.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Data::String(_) => "string",
+            Data::Integer(_) => "integer",
+            Data::Array(_) => "array",
+            Data::Null => "null",
+            Data::Error(_) => "error",
+            Data::Double(_) => "double",
+            Data::Boolean(_) => "boolean",
+            Data::BigNumber(_) => "big number",
+            Data::Verbatim { .. } => "verbatim string",
+            Data::Map(_) => "map",
+            Data::Set(_) => "set",
+            Data::Push(_) => "push",
+            Data::Attribute { .. } => "attribute",
+        }
+    }
 
-        return Ok(data);
+    /// Turns an error reply into the error [`Parser::next`] returns for it.
+    pub fn into_result(self) -> Result<Data, RedashError> {
+        match self {
+            Data::Error(line) => Err(RedashError::from_reply(&line)),
+            data => Ok(data),
+        }
     }
 
-    fn char(&self) -> Result<u8, RedashError> {
-        let mut buf = [0 as u8; 1];
-        match self.source.borrow_mut().read_exact(&mut buf) {
-            Ok(_) => Ok(buf[0]),
-            Err(err) => Err(RedashError::IOError(err)),
+    /// Takes the raw bytes out of a string reply.
+    pub fn into_bytes(self) -> Option<Vec<u8>> {
+        match self {
+            Data::String(s) => Some(Vec::from(s)),
+            Data::Verbatim { text, .. } => Some(text.into_bytes()),
+            _ => None,
         }
     }
+}
 
-    fn line(&self) -> Result<Vec<u8>, RedashError> {
-        let mut bytes: Vec<u8> = Vec::new();
-        let mut about_stop = false;
+impl From<&str> for Data {
+    fn from(s: &str) -> Self {
+        Data::String(Bytes::copy_from_slice(s.as_bytes()))
+    }
+}
 
-        loop {
-            let ch = self.char()?;
-            if about_stop {
-                break;
+impl From<String> for Data {
+    fn from(s: String) -> Self {
+        Data::from(s.into_bytes())
+    }
+}
+
+impl From<Vec<u8>> for Data {
+    fn from(bytes: Vec<u8>) -> Self {
+        Data::String(Bytes::from(bytes))
+    }
+}
+
+impl From<Bytes> for Data {
+    fn from(bytes: Bytes) -> Self {
+        Data::String(bytes)
+    }
+}
+
+/// Bounds on what a reply may announce, checked before anything is
+/// allocated for it so that a corrupt or hostile server cannot exhaust
+/// memory or the stack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParserLimits {
+    /// Largest payload of a bulk string, verbatim string or blob error. Lines
+    /// of simple types are bounded by it too.
+    pub max_bulk_len: usize,
+    /// Most elements of an array, set or push, or entries of a map.
+    pub max_aggregate_len: usize,
+    /// Most aggregates nested inside each other.
+    pub max_depth: usize,
+}
+
+impl Default for ParserLimits {
+    fn default() -> Self {
+        ParserLimits {
+            // the `proto-max-bulk-len` default of the server
+            max_bulk_len: 512 * 1024 * 1024,
+            // far more than any reply in practice, a hostile count is
+            // rejected before elements are buffered for it
+            max_aggregate_len: 1 << 20,
+            max_depth: 128,
+        }
+    }
+}
+
+/// Outcome of decoding the start of a buffer.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Decoded {
+    /// The buffer ends before the reply does.
+    Incomplete,
+    /// A complete reply and the number of bytes it spans.
+    Frame(Data, usize),
+}
+
+/// Decodes replies out of buffers that may hold only part of them, without
+/// blocking on a source.
+///
+/// The decoder remembers how far it got into an incomplete reply, so bytes
+/// already scanned are not scanned again when more arrive: until a frame is
+/// returned, every call must get the same buffer with more bytes appended.
+/// After a frame, the caller drops the consumed bytes before the next call.
+#[derive(Debug, Clone, Default)]
+pub struct FrameDecoder {
+    limits: ParserLimits,
+    // end of the elements scanned so far
+    scanned: usize,
+    // elements still expected by each aggregate being scanned
+    pending: Vec<u64>,
+}
+
+impl FrameDecoder {
+    pub fn new() -> Self {
+        FrameDecoder::default()
+    }
+
+    pub fn with_limits(limits: ParserLimits) -> Self {
+        FrameDecoder {
+            limits,
+            ..FrameDecoder::default()
+        }
+    }
+
+    /// Decodes the reply at the start of `buf`, copying it once so its
+    /// string payloads can share one allocation. Error replies are complete
+    /// frames holding a [`Data::Error`], see [`Data::into_result`]; only data
+    /// that is not valid RESP fails.
+    pub fn decode(&mut self, buf: &[u8]) -> Result<Decoded, RedashError> {
+        match self.frame_length(buf)? {
+            Some(length) => {
+                let frame = Bytes::copy_from_slice(&buf[..length]);
+                Ok(Decoded::Frame(parse_frame(&frame, self.limits)?, length))
             }
-            if ch == b'\r' {
-                about_stop = true;
+            None => Ok(Decoded::Incomplete),
+        }
+    }
+
+    /// Like [`FrameDecoder::decode`] without copying: a complete frame is
+    /// split off the front of `buf` and string payloads are slices of it.
+    pub fn decode_buf(&mut self, buf: &mut BytesMut) -> Result<Option<Data>, RedashError> {
+        match self.frame_length(buf)? {
+            Some(length) => {
+                let frame = buf.split_to(length).freeze();
+                parse_frame(&frame, self.limits).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn frame_length(&mut self, buf: &[u8]) -> Result<Option<usize>, RedashError> {
+        let scanned = match self.scan(buf) {
+            Ok(length) => Ok(Some(length)),
+            Err(Stop::Incomplete) => return Ok(None),
+            Err(Stop::Invalid(err)) => Err(err),
+        };
+        // the next frame is scanned from scratch
+        self.scanned = 0;
+        self.pending.clear();
+        scanned
+    }
+
+    /// Walks the headers of the elements of a reply, returning its length
+    /// once the last element is complete.
+    fn scan(&mut self, buf: &[u8]) -> Result<usize, Stop> {
+        loop {
+            let mut reader = Reader::new(buf, self.scanned, self.limits);
+            let children = reader.skip_element()?;
+            self.scanned = reader.pos;
+
+            if children > 0 {
+                if self.pending.len() == self.limits.max_depth {
+                    return Err(reader.invalid("nesting_exceeds_limit"));
+                }
+                self.pending.push(children);
                 continue;
             }
-            bytes.push(ch);
+            // a scalar is complete, and so is every aggregate it was the last
+            // element of
+            loop {
+                match self.pending.last_mut() {
+                    None => return Ok(self.scanned),
+                    Some(remaining) if *remaining > 1 => {
+                        *remaining -= 1;
+                        break;
+                    }
+                    Some(_) => {
+                        self.pending.pop();
+                    }
+                }
+            }
         }
+    }
+}
+
+/// Decodes one reply from the start of `buf`, see [`FrameDecoder`] to resume
+/// decoding as more bytes arrive.
+pub fn decode(buf: &[u8]) -> Result<Decoded, RedashError> {
+    FrameDecoder::new().decode(buf)
+}
 
-        return Ok(bytes);
+fn parse_frame(frame: &Bytes, limits: ParserLimits) -> Result<Data, RedashError> {
+    match FrameParser::new(frame, limits).data() {
+        Ok(data) => Ok(data),
+        Err(Stop::Invalid(err)) => Err(err),
+        // the scan found a complete frame, so this is a parser bug
+        Err(Stop::Incomplete) => Err(RedashError::ServerError(
+            String::from("inconsistent_frame"),
+            frame[0],
+        )),
     }
+}
+
+/// Why a reply could not be read out of a buffer.
+enum Stop {
+    Incomplete,
+    Invalid(RedashError),
+}
 
-    fn type_indicator(&self) -> Result<u8, RedashError> {
-        let ch = self.char()?;
-        return Ok(ch);
+impl From<RedashError> for Stop {
+    fn from(err: RedashError) -> Self {
+        Stop::Invalid(err)
     }
+}
+
+fn unknown<E: std::error::Error + Send + Sync + 'static>(err: E) -> Stop {
+    Stop::Invalid(RedashError::UnknownError(Box::new(err)))
+}
 
-    fn data_error(&self) -> Result<(), RedashError> {
-        let line = self.line()?;
-        match from_utf8(&line[..]) {
-            Ok(err_str) => Err(RedashError::DataError(String::from(err_str))),
-            Err(err) => Err(RedashError::UnknownError(Box::new(err))),
+struct Reader<'a> {
+    buf: &'a [u8],
+    pos: usize,
+    limits: ParserLimits,
+    // type indicator of the element being read, reported in errors
+    element: u8,
+}
+
+impl<'a> Reader<'a> {
+    fn new(buf: &'a [u8], pos: usize, limits: ParserLimits) -> Self {
+        Reader {
+            buf,
+            pos,
+            limits,
+            element: 0,
         }
     }
 
-    fn data_integer(&self) -> Result<Data, RedashError> {
-        let line = self.line()?;
-        let n_str = match from_utf8(&line[..]) {
-            Ok(n) => n,
-            Err(err) => return Err(RedashError::UnknownError(Box::new(err))),
+    fn invalid(&self, reason: &str) -> Stop {
+        Stop::Invalid(RedashError::ServerError(String::from(reason), self.element))
+    }
+
+    fn type_indicator(&mut self) -> Result<u8, Stop> {
+        let byte = *self.buf.get(self.pos).ok_or(Stop::Incomplete)?;
+        self.pos += 1;
+        self.element = byte;
+        Ok(byte)
+    }
+
+    /// Reads up to a CRLF, which must not be preceded by a lone CR or LF.
+    fn line(&mut self) -> Result<&'a [u8], Stop> {
+        let rest = &self.buf[self.pos..];
+        let end = match rest.iter().position(|&byte| byte == b'\r' || byte == b'\n') {
+            Some(end) if end > self.limits.max_bulk_len => {
+                return Err(self.invalid("line_exceeds_limit"))
+            }
+            Some(end) => end,
+            None if rest.len() > self.limits.max_bulk_len => {
+                return Err(self.invalid("line_exceeds_limit"))
+            }
+            None => return Err(Stop::Incomplete),
         };
+        match &rest[end..] {
+            [b'\r', b'\n', ..] => {
+                self.pos += end + 2;
+                Ok(&rest[..end])
+            }
+            [b'\r'] => Err(Stop::Incomplete),
+            _ => Err(self.invalid("invalid_line_ending")),
+        }
+    }
+
+    fn text_line(&mut self) -> Result<&'a str, Stop> {
+        from_utf8(self.line()?).map_err(unknown)
+    }
 
-        match str::parse(n_str.trim()) {
-            Ok(number) => Ok(Data::Integer(number)),
-            Err(err) => Err(RedashError::UnknownError(Box::new(err))),
+    fn integer(&mut self) -> Result<i64, Stop> {
+        self.text_line()?.parse().map_err(unknown)
+    }
+
+    /// Reads a length up to `limit`, `None` for the `-1` of a null.
+    fn length(&mut self, limit: usize) -> Result<Option<usize>, Stop> {
+        match self.integer()? {
+            -1 => Ok(None),
+            length => match usize::try_from(length) {
+                Ok(length) if length <= limit => Ok(Some(length)),
+                Ok(_) => Err(self.invalid("length_exceeds_limit")),
+                Err(_) => Err(self.invalid("invalid_length")),
+            },
         }
     }
 
-    fn data_simple_string(&self) -> Result<Data, RedashError> {
-        let line = self.line()?;
-        match from_utf8(&line[..]) {
-            Ok(s_str) => Ok(Data::String(String::from(s_str))),
-            Err(err) => return Err(RedashError::UnknownError(Box::new(err))),
+    /// Length of an aggregate, only arrays can be null.
+    fn count(&mut self) -> Result<Option<usize>, Stop> {
+        let count = self.length(self.limits.max_aggregate_len)?;
+        match count {
+            None if self.element != b'*' => Err(self.invalid("invalid_length")),
+            count => Ok(count),
         }
     }
 
-    fn data_bulk_string(&self) -> Result<Data, RedashError> {
-        let total_chars = if let Data::Integer(n) = self.data_integer()? {
-            n
-        } else {
-            -1
+    /// Reads a `<length>\r\n<payload>\r\n` body shared by bulk strings,
+    /// verbatim strings and blob errors. `None` means a null bulk string.
+    fn payload(&mut self) -> Result<Option<&'a [u8]>, Stop> {
+        let length = match self.length(self.limits.max_bulk_len)? {
+            Some(length) => length,
+            None if self.element == b'$' => return Ok(None),
+            None => return Err(self.invalid("invalid_length")),
         };
 
-        if total_chars == -1 {
-            return Ok(Data::Null);
+        let rest = &self.buf[self.pos..];
+        // the payload is followed by CRLF
+        if rest.len() < 2 || rest.len() - 2 < length {
+            return Err(Stop::Incomplete);
+        }
+        if rest[length..length + 2] != *b"\r\n" {
+            return Err(self.invalid("invalid_line_ending"));
         }
+        self.pos += length + 2;
+        Ok(Some(&rest[..length]))
+    }
 
-        let mut bytes: Vec<u8> = Vec::with_capacity(total_chars as usize);
-        for _ in 0..total_chars {
-            bytes.push(self.char()?);
+    /// Skips the header and scalar body of the next element, returning how
+    /// many nested elements follow it.
+    fn skip_element(&mut self) -> Result<u64, Stop> {
+        match self.type_indicator()? {
+            b'+' | b'-' | b':' | b'_' | b',' | b'#' | b'(' => {
+                self.line()?;
+                Ok(0)
+            }
+            b'$' | b'=' | b'!' => {
+                self.payload()?;
+                Ok(0)
+            }
+            b'*' | b'~' | b'>' => Ok(self.count()?.unwrap_or(0) as u64),
+            b'%' => Ok(self.count()?.unwrap_or(0) as u64 * 2),
+            // the attributes are followed by the reply they decorate
+            b'|' => Ok(self.count()?.unwrap_or(0) as u64 * 2 + 1),
+            _ => Err(self.invalid("invalid_server_data_type")),
         }
-        // swallow CRLF
-        for _ in 0..2 {
-            self.char()?;
+    }
+}
+
+/// Builds the data of a complete frame, slicing string payloads out of it.
+struct FrameParser<'a> {
+    frame: &'a Bytes,
+    reader: Reader<'a>,
+}
+
+impl<'a> FrameParser<'a> {
+    fn new(frame: &'a Bytes, limits: ParserLimits) -> Self {
+        FrameParser {
+            frame,
+            reader: Reader::new(frame, 0, limits),
         }
+    }
 
-        match from_utf8(&bytes[..]) {
-            Ok(s_str) => Ok(Data::String(String::from(s_str))),
-            Err(err) => return Err(RedashError::UnknownError(Box::new(err))),
+    fn data(&mut self) -> Result<Data, Stop> {
+        match self.reader.type_indicator()? {
+            b'+' => Ok(Data::String(self.frame.slice_ref(self.reader.line()?))),
+            b'-' => Ok(Data::Error(String::from(self.reader.text_line()?))),
+            b':' => Ok(Data::Integer(self.reader.integer()?)),
+            b'$' => match self.reader.payload()? {
+                Some(bytes) => Ok(Data::String(self.frame.slice_ref(bytes))),
+                None => Ok(Data::Null),
+            },
+            b'*' => match self.reader.count()? {
+                Some(count) => self.items(count, Data::Array),
+                None => Ok(Data::Null),
+            },
+            b'_' => match self.reader.line()? {
+                b"" => Ok(Data::Null),
+                _ => Err(self.reader.invalid("invalid_null")),
+            },
+            b',' => self.double(),
+            b'#' => match self.reader.line()? {
+                b"t" => Ok(Data::Boolean(true)),
+                b"f" => Ok(Data::Boolean(false)),
+                _ => Err(self.reader.invalid("invalid_boolean_value")),
+            },
+            b'(' => Ok(Data::BigNumber(String::from(self.reader.text_line()?))),
+            b'=' => self.verbatim_string(),
+            b'!' => {
+                let bytes = self.reader.payload()?.unwrap_or_default();
+                Ok(Data::Error(String::from(
+                    from_utf8(bytes).map_err(unknown)?,
+                )))
+            }
+            b'%' => {
+                let count = self.reader.count()?.unwrap_or(0);
+                Ok(Data::Map(self.pairs(count)?))
+            }
+            b'~' => {
+                let count = self.reader.count()?.unwrap_or(0);
+                self.items(count, Data::Set)
+            }
+            b'>' => {
+                let count = self.reader.count()?.unwrap_or(0);
+                self.items(count, Data::Push)
+            }
+            b'|' => {
+                let count = self.reader.count()?.unwrap_or(0);
+                let attributes = self.pairs(count)?;
+                Ok(Data::Attribute {
+                    attributes,
+                    data: Box::new(self.data()?),
+                })
+            }
+            _ => Err(self.reader.invalid("invalid_server_data_type")),
         }
     }
 
-    fn data_array(&self) -> Result<Data, RedashError> {
-        let total_items = if let Data::Integer(n) = self.data_integer()? {
-            n
-        } else {
-            -1
+    fn double(&mut self) -> Result<Data, Stop> {
+        let number = match self.reader.text_line()? {
+            "inf" => f64::INFINITY,
+            "-inf" => f64::NEG_INFINITY,
+            "nan" => f64::NAN,
+            n => n.parse().map_err(unknown)?,
         };
+        Ok(Data::Double(number))
+    }
 
-        if total_items == -1 {
-            return Ok(Data::Null);
+    fn verbatim_string(&mut self) -> Result<Data, Stop> {
+        let bytes = self.reader.payload()?.unwrap_or_default();
+        let s_str = from_utf8(bytes).map_err(unknown)?;
+
+        // the payload is `xxx:<text>` where `xxx` is the format
+        match s_str.split_once(':') {
+            Some((format, text)) if format.len() == 3 => Ok(Data::Verbatim {
+                format: String::from(format),
+                text: String::from(text),
+            }),
+            _ => Err(self.reader.invalid("invalid_verbatim_string")),
         }
+    }
 
-        let mut items: Vec<Box<Data>> = Vec::with_capacity(total_items as usize);
-        for _ in 0..total_items {
-            let item = self.next()?;
-            items.push(Box::new(item));
+    // The scan checked that the frame holds every element, so the counts
+    // are bounded by its size.
+    //
+    // Error replies inside aggregates stay `Data::Error` items, the rest of
+    // the aggregate follows them.
+    fn items(&mut self, count: usize, aggregate: fn(Vec<Data>) -> Data) -> Result<Data, Stop> {
+        let mut items = Vec::with_capacity(count);
+        for _ in 0..count {
+            items.push(self.data()?);
         }
+        Ok(aggregate(items))
+    }
 
-        return Ok(Data::Array(items));
+    fn pairs(&mut self, count: usize) -> Result<Vec<(Data, Data)>, Stop> {
+        let mut pairs = Vec::with_capacity(count);
+        for _ in 0..count {
+            let key = self.data()?;
+            let value = self.data()?;
+            pairs.push((key, value));
+        }
+        Ok(pairs)
+    }
+}
+
+/// Size of the reads from the source of a [`Parser`]; large replies take
+/// several reads.
+const READ_SIZE: usize = 64 * 1024;
+
+impl<T: Read> Parser<T> {
+    pub fn new(source: T) -> Self {
+        Parser::with_limits(source, ParserLimits::default())
+    }
+
+    pub fn with_limits(source: T, limits: ParserLimits) -> Self {
+        Parser {
+            source: RefCell::new(source),
+            buffer: RefCell::new(BytesMut::new()),
+            chunk: RefCell::new(Vec::new()),
+            decoder: RefCell::new(FrameDecoder::with_limits(limits)),
+        }
+    }
+
+    /// Mutable access to the underlying source, bypassing the read buffer.
+    pub fn get_mut(&self) -> RefMut<'_, T> {
+        self.source.borrow_mut()
+    }
+
+    /// Number of bytes read from the source but not parsed yet.
+    pub fn buffered(&self) -> usize {
+        self.buffer.borrow().len()
+    }
+
+    /// Reads the next reply, blocking on the source until it is complete.
+    /// Error replies are returned as errors. Bytes of a reply received before
+    /// a read fails, e.g. on timeout, are kept for the next call.
+    pub fn next(&self) -> Result<Data, RedashError> {
+        let mut buffer = self.buffer.borrow_mut();
+        let mut decoder = self.decoder.borrow_mut();
+        let mut chunk = self.chunk.borrow_mut();
+        loop {
+            if let Some(data) = decoder.decode_buf(&mut buffer)? {
+                return data.into_result();
+            }
+
+            if chunk.is_empty() {
+                chunk.resize(READ_SIZE, 0);
+            }
+            // the buffer grows into its spare capacity, nothing is zeroed
+            match self.source.borrow_mut().read(&mut chunk) {
+                Ok(0) => return Err(RedashError::IOError(ErrorKind::UnexpectedEof.into())),
+                Ok(read) => buffer.extend_from_slice(&chunk[..read]),
+                Err(err) if err.kind() == ErrorKind::Interrupted => {}
+                Err(err) => return Err(RedashError::IOError(err)),
+            }
+        }
     }
 }
 
@@ -179,36 +625,347 @@ impl<T: Read> Parser<T> {
 mod tests {
     use super::*;
 
+    /// Hands out `subject` a few bytes per read, `cursor` being how much
+    /// was read so far.
     struct FakeSource {
-        cursor: u16,
+        cursor: usize,
         subject: Vec<u8>,
     }
 
     impl FakeSource {
         fn new() -> Self {
-            return FakeSource {
+            FakeSource {
                 cursor: 0,
                 subject: Vec::from(""),
-            };
+            }
         }
     }
 
     impl Read for FakeSource {
         fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
-            for i in 0..self.subject.len() {
-                buf[i] = self.subject[i];
-            }
-            self.cursor += 1;
-            return Ok(buf.len());
+            let rest = &self.subject[self.cursor..];
+            let len = rest.len().min(buf.len()).min(4);
+            buf[..len].copy_from_slice(&rest[..len]);
+            self.cursor += len;
+            Ok(len)
         }
     }
 
+    fn parse(input: &[u8]) -> Result<Data, RedashError> {
+        Parser::new(input).next()
+    }
+
+    fn string(s: &str) -> Data {
+        Data::from(s)
+    }
+
     #[test]
-    fn test_read_char() {
+    fn test_read_from_source() {
         let mut source = FakeSource::new();
-        source.subject = Vec::from("abc");
+        source.subject = Vec::from("+abc\r\n$5\r\nhello\r\n");
         let parser = Parser::new(source);
-        let ch = parser.char().unwrap();
-        assert_eq!(ch, b'a');
+        assert_eq!(parser.next().unwrap(), string("abc"));
+        assert_eq!(parser.next().unwrap(), string("hello"));
+        assert_eq!(parser.get_mut().cursor, 17);
+        assert!(parser.next().is_err());
+    }
+
+    #[test]
+    fn test_decode_partial_input() {
+        let input = b"*3\r\n$5\r\nhello\r\n%1\r\n+k\r\n:1\r\n-ERR nested\r\n+next\r\n";
+        let frame_length = input.len() - b"+next\r\n".len();
+        for end in 0..frame_length {
+            assert_eq!(decode(&input[..end]).unwrap(), Decoded::Incomplete, "{end}");
+        }
+        assert_eq!(
+            decode(input).unwrap(),
+            Decoded::Frame(
+                Data::Array(vec![
+                    string("hello"),
+                    Data::Map(vec![(string("k"), Data::Integer(1))]),
+                    Data::Error(String::from("ERR nested")),
+                ]),
+                frame_length
+            )
+        );
+    }
+
+    #[test]
+    fn test_frame_decoder_resumes() {
+        let input = b"*2\r\n$3\r\nfoo\r\n*-1\r\n-ERR top\r\n";
+        let mut decoder = FrameDecoder::new();
+        let mut buf = Vec::new();
+        let mut frames = Vec::new();
+        for &byte in input {
+            buf.push(byte);
+            if let Decoded::Frame(data, consumed) = decoder.decode(&buf).unwrap() {
+                buf.drain(..consumed);
+                frames.push(data);
+            }
+        }
+        assert!(buf.is_empty());
+        assert_eq!(
+            frames,
+            vec![
+                Data::Array(vec![string("foo"), Data::Null]),
+                Data::Error(String::from("ERR top")),
+            ]
+        );
+        assert!(matches!(
+            frames.pop().unwrap().into_result(),
+            Err(RedashError::DataError(_))
+        ));
+    }
+
+    #[test]
+    fn test_payloads_share_the_frame() {
+        let mut buf = BytesMut::from(&b"*2\r\n$3\r\nfoo\r\n+bar\r\n:1\r\n"[..]);
+        let start = buf.as_ptr() as usize;
+        let items = match FrameDecoder::new().decode_buf(&mut buf).unwrap() {
+            Some(Data::Array(items)) => items,
+            other => panic!("unexpected result: {other:?}"),
+        };
+        assert_eq!(items, vec![string("foo"), string("bar")]);
+        // the payloads point into the buffer the frame was read into
+        let offsets: Vec<usize> = items
+            .iter()
+            .map(|item| item.as_bytes().unwrap().as_ptr() as usize - start)
+            .collect();
+        assert_eq!(offsets, vec![8, 14]);
+        assert_eq!(&buf[..], b":1\r\n");
+    }
+
+    #[test]
+    fn test_decode_invalid_type() {
+        assert!(matches!(
+            decode(b"?1\r\n"),
+            Err(RedashError::ServerError(_, b'?'))
+        ));
+    }
+
+    fn protocol_error(result: Result<Decoded, RedashError>) -> String {
+        match result {
+            Err(RedashError::ServerError(reason, _)) => reason,
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_strict_line_endings() {
+        for input in [
+            &b"+OK\rX\r\n"[..],
+            b"+OK\n",
+            b":1\n\r\n",
+            b"$2\r\nabXY",
+            b"$2\r\nab\n\r",
+        ] {
+            assert_eq!(
+                protocol_error(decode(input)),
+                "invalid_line_ending",
+                "{input:?}"
+            );
+        }
+        // the line feed may come with the next read
+        assert_eq!(decode(b"+OK\r").unwrap(), Decoded::Incomplete);
+    }
+
+    #[test]
+    fn test_invalid_lengths() {
+        for input in [
+            &b"$-2\r\n"[..],
+            b"*-5\r\n",
+            b"%-1\r\n",
+            b"=-1\r\n",
+            b"~-1\r\n",
+        ] {
+            assert_eq!(protocol_error(decode(input)), "invalid_length", "{input:?}");
+        }
+        assert!(matches!(
+            decode(b"* 1\r\n"),
+            Err(RedashError::UnknownError(_))
+        ));
+        assert_eq!(
+            protocol_error(decode(b"$9999999999999999\r\n")),
+            "length_exceeds_limit"
+        );
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = ParserLimits {
+            max_bulk_len: 4,
+            max_aggregate_len: 2,
+            max_depth: 2,
+        };
+        let decode = |input: &[u8]| FrameDecoder::with_limits(limits).decode(input);
+
+        assert!(matches!(decode(b"$4\r\nabcd\r\n"), Ok(Decoded::Frame(..))));
+        // rejected from the header, before the payload arrives
+        assert_eq!(protocol_error(decode(b"$5\r\n")), "length_exceeds_limit");
+        assert_eq!(protocol_error(decode(b"%3\r\n")), "length_exceeds_limit");
+        assert_eq!(protocol_error(decode(b"+abcde")), "line_exceeds_limit");
+        assert_eq!(
+            protocol_error(decode(b"-ERR abc\r\n")),
+            "line_exceeds_limit"
+        );
+        assert!(matches!(
+            decode(b"*1\r\n*1\r\n:1\r\n"),
+            Ok(Decoded::Frame(..))
+        ));
+        assert_eq!(
+            protocol_error(decode(b"*1\r\n*1\r\n*1\r\n")),
+            "nesting_exceeds_limit"
+        );
+        assert_eq!(
+            protocol_error(decode(&b"*1\r\n".repeat(100_000))),
+            "nesting_exceeds_limit"
+        );
+    }
+
+    #[test]
+    fn test_default_limits_reject_hostile_lengths() {
+        assert_eq!(
+            protocol_error(decode(b"*4294967295\r\n")),
+            "length_exceeds_limit"
+        );
+    }
+
+    #[test]
+    fn test_corrupt_input_does_not_panic() {
+        let reply = b"*3\r\n$5\r\nhello\r\n%1\r\n+k\r\n,1.5\r\n|1\r\n+a\r\n=7\r\ntxt:abc\r\n#t\r\n";
+        assert!(matches!(decode(reply), Ok(Decoded::Frame(..))));
+        for i in 0..reply.len() {
+            for byte in [0, b'\r', b'\n', b'-', b'9', b'*', b'|', 0xff] {
+                let mut input = reply.to_vec();
+                input[i] = byte;
+                let _ = decode(&input);
+                let _ = Parser::new(&input[..]).next();
+                let _ = decode(&input[..i]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_parser_keeps_partial_reply_after_read_error() {
+        struct Chunks(Vec<std::io::Result<Vec<u8>>>);
+
+        impl Read for Chunks {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                let chunk = self.0.remove(0)?;
+                buf[..chunk.len()].copy_from_slice(&chunk);
+                Ok(chunk.len())
+            }
+        }
+
+        let parser = Parser::new(Chunks(vec![
+            Ok(b"$5\r\nhel".to_vec()),
+            Err(ErrorKind::WouldBlock.into()),
+            Ok(b"lo\r\n".to_vec()),
+        ]));
+        assert!(matches!(parser.next(), Err(RedashError::IOError(_))));
+        assert_eq!(parser.buffered(), 7);
+        assert_eq!(parser.next().unwrap(), string("hello"));
+        assert_eq!(parser.buffered(), 0);
+    }
+
+    #[test]
+    fn test_binary_bulk_string() {
+        let data = parse(b"$4\r\n\x00\xff\r\n\r\n").unwrap();
+        assert_eq!(data, Data::from(vec![0x00, 0xff, b'\r', b'\n']));
+        assert_eq!(data.as_bytes(), Some(&[0x00, 0xff, b'\r', b'\n'][..]));
+        assert_eq!(data.as_str(), None);
+        assert_eq!(format!("{data}"), "\"\\x00\\xff\\r\\n\"");
+
+        let data = parse(b"$5\r\nhello\r\n").unwrap();
+        assert_eq!(data.as_str(), Some("hello"));
+        assert_eq!(format!("{data}"), "hello");
+    }
+
+    #[test]
+    fn test_resp3_scalars() {
+        assert_eq!(parse(b"_\r\n").unwrap(), Data::Null);
+        assert_eq!(parse(b",3.25\r\n").unwrap(), Data::Double(3.25));
+        assert_eq!(
+            parse(b",-inf\r\n").unwrap(),
+            Data::Double(f64::NEG_INFINITY)
+        );
+        assert_eq!(parse(b"#t\r\n").unwrap(), Data::Boolean(true));
+        assert_eq!(parse(b"#f\r\n").unwrap(), Data::Boolean(false));
+        assert_eq!(
+            parse(b"(3492890328409238509324850943850943825024385\r\n").unwrap(),
+            Data::BigNumber(String::from("3492890328409238509324850943850943825024385"))
+        );
+        assert_eq!(
+            parse(b"=15\r\ntxt:Some string\r\n").unwrap(),
+            Data::Verbatim {
+                format: String::from("txt"),
+                text: String::from("Some string"),
+            }
+        );
+    }
+
+    #[test]
+    fn test_resp3_blob_error() {
+        match parse(b"!21\r\nSYNTAX invalid syntax\r\n") {
+            Err(RedashError::DataError(err)) => {
+                assert_eq!(err.code(), "SYNTAX");
+                assert_eq!(err.message(), "invalid syntax");
+            }
+            other => panic!("unexpected result: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_resp3_aggregates() {
+        assert_eq!(
+            parse(b"%2\r\n+first\r\n:1\r\n$6\r\nsecond\r\n:2\r\n").unwrap(),
+            Data::Map(vec![
+                (string("first"), Data::Integer(1)),
+                (string("second"), Data::Integer(2)),
+            ])
+        );
+        assert_eq!(
+            parse(b"~2\r\n+a\r\n#t\r\n").unwrap(),
+            Data::Set(vec![string("a"), Data::Boolean(true)])
+        );
+        assert_eq!(
+            parse(b">3\r\n+message\r\n+chan\r\n+hi\r\n").unwrap(),
+            Data::Push(vec![string("message"), string("chan"), string("hi"),])
+        );
+    }
+
+    #[test]
+    fn test_auth_errors() {
+        assert!(matches!(
+            parse(b"-NOAUTH Authentication required.\r\n"),
+            Err(RedashError::AuthRequired(_))
+        ));
+        assert!(matches!(
+            parse(b"-WRONGPASS invalid username-password pair\r\n"),
+            Err(RedashError::AuthFailed(_))
+        ));
+        assert!(matches!(
+            parse(b"-ERR unknown command\r\n"),
+            Err(RedashError::DataError(_))
+        ));
+    }
+
+    #[test]
+    fn test_nested_error() {
+        assert_eq!(
+            parse(b"*2\r\n+OK\r\n-ERR wrong\r\n").unwrap(),
+            Data::Array(vec![string("OK"), Data::Error(String::from("ERR wrong")),])
+        );
+    }
+
+    #[test]
+    fn test_resp3_attribute() {
+        assert_eq!(
+            parse(b"|1\r\n+ttl\r\n:3600\r\n:42\r\n").unwrap(),
+            Data::Attribute {
+                attributes: vec![(string("ttl"), Data::Integer(3600))],
+                data: Box::new(Data::Integer(42)),
+            }
+        );
     }
 }
@@ -0,0 +1,115 @@
+use super::{command, connection::Connection, errors::RedashError, parser::Data, Client};
+
+/// Queues commands and sends them in a single write, reading all replies
+/// afterwards.
+///
+/// # Examples
+/// ```no_run
+/// # use redash_client::client::Client;
+/// # let client = Client::new("127.0.0.1", 6379);
+/// let mut pipeline = client.pipeline();
+/// pipeline.args(&["SET", "a", "1"]).args(&["INCR", "a"]);
+/// let replies = pipeline.execute().unwrap();
+/// ```
+#[derive(Clone)]
+pub struct Pipeline<'a> {
+    client: &'a Client,
+    commands: Vec<Vec<Vec<u8>>>,
+}
+
+impl<'a> Pipeline<'a> {
+    pub fn new(client: &'a Client) -> Self {
+        Pipeline {
+            client,
+            commands: Vec::new(),
+        }
+    }
+
+    /// Queues a `redis-cli` style command line.
+    pub fn command(&mut self, command: &str) -> Result<&mut Self, RedashError> {
+        let args = command::tokenize(command)?;
+        if args.is_empty() {
+            return Err(RedashError::OperationError(String::from("empty command")));
+        }
+        self.commands.push(args);
+        Ok(self)
+    }
+
+    /// Queues already split arguments.
+    pub fn args<A: AsRef<[u8]>>(&mut self, args: &[A]) -> &mut Self {
+        self.commands
+            .push(args.iter().map(|arg| arg.as_ref().to_vec()).collect());
+        self
+    }
+
+    pub(crate) fn client(&self) -> &'a Client {
+        self.client
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.commands.clear()
+    }
+
+    /// Sends every queued command and returns the replies in order.
+    ///
+    /// Server errors are kept per command; the outer error is only returned
+    /// when the connection itself fails.
+    pub fn execute(&self) -> Result<Vec<Result<Data, RedashError>>, RedashError> {
+        self.client
+            .with_connection(|connection| self.execute_on(connection))
+    }
+
+    /// Sends the queued commands on a connection the caller already holds.
+    pub(crate) fn execute_on(
+        &self,
+        connection: &Connection,
+    ) -> Result<Vec<Result<Data, RedashError>>, RedashError> {
+        connection.send_all(&self.commands)?;
+
+        let mut replies = Vec::with_capacity(self.commands.len());
+        for _ in 0..self.commands.len() {
+            match connection.read() {
+                Err(err) if err.reply().is_none() => return Err(err),
+                reply => replies.push(reply),
+            }
+        }
+        Ok(replies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::fake_server::FakeServer;
+
+    #[test]
+    fn test_pipeline_keeps_server_errors_in_order() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+
+        let mut pipeline = client.pipeline();
+        pipeline
+            .args(&["SET", "a", "x"])
+            .args(&["INCR", "a"])
+            .command("RPUSH l \"one two\" three")
+            .unwrap();
+        let replies = pipeline.execute().unwrap();
+
+        assert_eq!(replies.len(), 3);
+        assert_eq!(replies[0].as_ref().unwrap(), &Data::from("OK"));
+        assert!(matches!(&replies[1], Err(RedashError::DataError(err)) if err.code() == "ERR"));
+        assert_eq!(replies[2].as_ref().unwrap(), &Data::Integer(2));
+        assert_eq!(
+            server.commands(),
+            ["SET a x", "INCR a", "RPUSH l one two three"]
+        );
+    }
+}
@@ -0,0 +1,342 @@
+use std::{
+    collections::VecDeque,
+    ops::Deref,
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use super::{
+    errors::RedashError, lock, options::ConnectionOptions, parser::Data,
+    reconnect::ReconnectPolicy, Client,
+};
+
+/// Limits and checks applied by a [`Pool`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolOptions {
+    /// Connections open at the same time, idle or checked out.
+    pub max_size: usize,
+    /// Idle connections older than this are closed, `None` keeps them.
+    pub idle_timeout: Option<Duration>,
+    /// How long a checkout waits for a connection when the pool is full.
+    pub checkout_timeout: Duration,
+    /// Sends `PING` before handing out an idle connection.
+    pub health_check: bool,
+}
+
+impl Default for PoolOptions {
+    fn default() -> Self {
+        PoolOptions {
+            max_size: 10,
+            idle_timeout: Some(Duration::from_secs(300)),
+            checkout_timeout: Duration::from_secs(30),
+            health_check: true,
+        }
+    }
+}
+
+/// Counters reported by [`Pool::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Connections currently open, idle or checked out.
+    pub open: usize,
+    pub idle: usize,
+    pub in_use: usize,
+    pub checkouts: u64,
+    pub created: u64,
+    pub closed: u64,
+    /// Idle connections that did not answer `PING` and were closed.
+    pub health_check_failures: u64,
+    /// Connections returned mid-`MULTI`, watching, subscribed or on another
+    /// database, which were closed instead of being reused.
+    pub resets: u64,
+    /// Checkouts that gave up because the pool stayed full.
+    pub timeouts: u64,
+}
+
+struct IdleClient {
+    client: Client,
+    since: Instant,
+}
+
+struct PoolState {
+    idle: VecDeque<IdleClient>,
+    stats: PoolStats,
+}
+
+impl PoolState {
+    fn close(&mut self, count: usize) {
+        self.stats.open -= count;
+        self.stats.closed += count as u64;
+    }
+
+    fn evict_idle(&mut self, idle_timeout: Option<Duration>) {
+        if let Some(idle_timeout) = idle_timeout {
+            // the oldest connections are at the front
+            let before = self.idle.len();
+            self.idle.retain(|idle| idle.since.elapsed() < idle_timeout);
+            self.close(before - self.idle.len());
+        }
+    }
+}
+
+/// Manages up to `max_size` connections opened from one configuration.
+///
+/// Connections are opened lazily and handed out as [`PooledClient`]s, which
+/// return to the pool when dropped. They do not reconnect on their own: a
+/// broken connection is closed and the pool opens a new one instead.
+///
+/// # Examples
+/// ```no_run
+/// # use redash_client::client::{options::ConnectionOptions, pool::{Pool, PoolOptions}};
+/// let pool = Pool::new(ConnectionOptions::new("127.0.0.1", 6379), PoolOptions::default());
+/// let client = pool.get().unwrap();
+/// client.send_command("INCR jobs").unwrap();
+/// ```
+pub struct Pool {
+    options: ConnectionOptions,
+    pool_options: PoolOptions,
+    state: Mutex<PoolState>,
+    returned: Condvar,
+}
+
+impl Pool {
+    pub fn new(options: ConnectionOptions, pool_options: PoolOptions) -> Self {
+        Pool {
+            options,
+            pool_options,
+            state: Mutex::new(PoolState {
+                idle: VecDeque::new(),
+                stats: PoolStats::default(),
+            }),
+            returned: Condvar::new(),
+        }
+    }
+
+    pub fn options(&self) -> &ConnectionOptions {
+        &self.options
+    }
+
+    pub fn pool_options(&self) -> &PoolOptions {
+        &self.pool_options
+    }
+
+    /// Checks out a connection, reusing an idle one when possible and
+    /// waiting up to `checkout_timeout` when the pool is full.
+    pub fn get(&self) -> Result<PooledClient<'_>, RedashError> {
+        let deadline = Instant::now() + self.pool_options.checkout_timeout;
+        let mut state = lock(&self.state);
+        loop {
+            state.evict_idle(self.pool_options.idle_timeout);
+
+            // the most recently returned connection is the least likely to be stale
+            if let Some(IdleClient { client, .. }) = state.idle.pop_back() {
+                if self.pool_options.health_check {
+                    drop(state);
+                    let healthy = matches!(client.send_command("PING"), Ok(Data::String(_)));
+                    state = lock(&self.state);
+                    if !healthy {
+                        state.close(1);
+                        state.stats.health_check_failures += 1;
+                        continue;
+                    }
+                }
+                state.stats.checkouts += 1;
+                state.stats.in_use += 1;
+                return Ok(PooledClient::new(self, client));
+            }
+
+            if state.stats.open < self.pool_options.max_size {
+                // reserve the slot while connecting without holding the lock
+                state.stats.open += 1;
+                drop(state);
+                let client = Client::with_options(ConnectionOptions {
+                    reconnect: ReconnectPolicy::disabled(),
+                    ..self.options.clone()
+                });
+                let result = client.connect();
+
+                let mut state = lock(&self.state);
+                if let Err(err) = result {
+                    state.stats.open -= 1;
+                    self.returned.notify_one();
+                    return Err(err);
+                }
+                state.stats.created += 1;
+                state.stats.checkouts += 1;
+                state.stats.in_use += 1;
+                return Ok(PooledClient::new(self, client));
+            }
+
+            let now = Instant::now();
+            if now >= deadline {
+                state.stats.timeouts += 1;
+                return Err(RedashError::OperationError(String::from("pool_timeout")));
+            }
+            state = self
+                .returned
+                .wait_timeout(state, deadline - now)
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .0;
+        }
+    }
+
+    pub fn stats(&self) -> PoolStats {
+        let mut state = lock(&self.state);
+        state.evict_idle(self.pool_options.idle_timeout);
+        PoolStats {
+            idle: state.idle.len(),
+            ..state.stats
+        }
+    }
+
+    fn put_back(&self, client: Client) {
+        let dirty = client.session_state().is_dirty() || client.database() != self.options.database;
+        let mut state = lock(&self.state);
+        state.stats.in_use -= 1;
+        if dirty {
+            // closing the connection is the only reset that also undoes a
+            // subscription or a pending transaction on every server version
+            state.close(1);
+            state.stats.resets += 1;
+        } else if !client.is_connected() {
+            state.close(1);
+        } else {
+            state.idle.push_back(IdleClient {
+                client,
+                since: Instant::now(),
+            });
+        }
+        self.returned.notify_one();
+    }
+}
+
+/// A connection checked out of a [`Pool`], returned when dropped.
+pub struct PooledClient<'a> {
+    pool: &'a Pool,
+    client: Option<Client>,
+}
+
+impl<'a> PooledClient<'a> {
+    fn new(pool: &'a Pool, client: Client) -> Self {
+        PooledClient {
+            pool,
+            client: Some(client),
+        }
+    }
+}
+
+impl<'a> Deref for PooledClient<'a> {
+    type Target = Client;
+
+    fn deref(&self) -> &Client {
+        self.client
+            .as_ref()
+            .expect("the client is only taken on drop")
+    }
+}
+
+impl<'a> Drop for PooledClient<'a> {
+    fn drop(&mut self) {
+        if let Some(client) = self.client.take() {
+            self.pool.put_back(client);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::fake_server::FakeServer;
+
+    /// A pool of connections to a new server, which stops when dropped.
+    fn pool(pool_options: PoolOptions) -> (FakeServer, Pool) {
+        let server = FakeServer::start().unwrap();
+        let pool = Pool::new(server.options(), pool_options);
+        (server, pool)
+    }
+
+    #[test]
+    fn test_checkout_waits_for_a_free_connection() {
+        let (_server, pool) = pool(PoolOptions {
+            max_size: 2,
+            checkout_timeout: Duration::from_millis(20),
+            ..PoolOptions::default()
+        });
+
+        let first = pool.get().unwrap();
+        let second = pool.get().unwrap();
+        assert!(matches!(pool.get(), Err(RedashError::OperationError(_))));
+        assert_eq!(pool.stats().in_use, 2);
+
+        drop(first);
+        let third = pool.get().unwrap();
+        assert_eq!(third.send_command("SET a 1").unwrap(), Data::from("OK"));
+        drop((second, third));
+
+        let stats = pool.stats();
+        assert_eq!((stats.open, stats.idle, stats.in_use), (2, 2, 0));
+        assert_eq!((stats.created, stats.checkouts, stats.timeouts), (2, 3, 1));
+    }
+
+    #[test]
+    fn test_dirty_connections_are_not_reused() {
+        let (_server, pool) = pool(PoolOptions::default());
+
+        let client = pool.get().unwrap();
+        client.send_command("MULTI").unwrap();
+        assert!(client.session_state().in_transaction);
+        drop(client);
+
+        let stats = pool.stats();
+        assert_eq!((stats.open, stats.idle, stats.resets), (0, 0, 1));
+
+        let client = pool.get().unwrap();
+        assert!(!client.session_state().is_dirty());
+        assert_eq!(pool.stats().created, 2);
+    }
+
+    #[test]
+    fn test_connections_on_another_database_are_not_reused() {
+        let (_server, pool) = pool(PoolOptions::default());
+
+        let client = pool.get().unwrap();
+        client.send_command("SELECT 5").unwrap();
+        client.send_command("SET k v").unwrap();
+        drop(client);
+        assert_eq!(pool.stats().resets, 1);
+
+        let client = pool.get().unwrap();
+        assert_eq!(client.database(), None);
+        assert_eq!(client.send_command("GET k").unwrap(), Data::Null);
+    }
+
+    #[test]
+    fn test_broken_idle_connections_fail_the_health_check() {
+        let (server, pool) = pool(PoolOptions::default());
+
+        let client = pool.get().unwrap();
+        client.send_command("PING").unwrap();
+        drop(client);
+        server.disconnect_all();
+        let client = pool.get().unwrap();
+        assert_eq!(client.send_command("PING").unwrap(), Data::from("PONG"));
+
+        let stats = pool.stats();
+        assert_eq!((stats.health_check_failures, stats.closed), (1, 1));
+        assert_eq!((stats.created, stats.open), (2, 1));
+    }
+
+    #[test]
+    fn test_idle_connections_expire() {
+        let (_server, pool) = pool(PoolOptions {
+            idle_timeout: Some(Duration::ZERO),
+            ..PoolOptions::default()
+        });
+
+        drop(pool.get().unwrap());
+        drop(pool.get().unwrap());
+
+        let stats = pool.stats();
+        assert_eq!((stats.created, stats.closed, stats.open), (2, 2, 0));
+    }
+}
@@ -0,0 +1,421 @@
+use std::collections::{HashSet, VecDeque};
+
+use super::{connection::Connection, errors::RedashError, parser::Data, Client};
+
+/// A message delivered to a subscriber.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Message {
+    /// Published on a channel subscribed with `SUBSCRIBE`.
+    Message { channel: Vec<u8>, payload: Vec<u8> },
+    /// Published on a channel matching a `PSUBSCRIBE` pattern.
+    PMessage {
+        pattern: Vec<u8>,
+        channel: Vec<u8>,
+        payload: Vec<u8>,
+    },
+    /// Published on a shard channel subscribed with `SSUBSCRIBE`.
+    SMessage { channel: Vec<u8>, payload: Vec<u8> },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Channel,
+    Pattern,
+    Shard,
+}
+
+impl Kind {
+    fn subscribe_command(&self) -> &'static str {
+        match self {
+            Kind::Channel => "SUBSCRIBE",
+            Kind::Pattern => "PSUBSCRIBE",
+            Kind::Shard => "SSUBSCRIBE",
+        }
+    }
+
+    fn unsubscribe_command(&self) -> &'static str {
+        match self {
+            Kind::Channel => "UNSUBSCRIBE",
+            Kind::Pattern => "PUNSUBSCRIBE",
+            Kind::Shard => "SUNSUBSCRIBE",
+        }
+    }
+}
+
+/// Frames received while in subscribe mode.
+enum Frame {
+    Message(Message),
+    /// Subscribe or unsubscribe confirmation for a single name.
+    Confirmation,
+    Other,
+}
+
+/// Subscribes to channels on a connection of its own and yields published
+/// messages as an iterator. The client stays usable for other commands.
+///
+/// The connection waits for messages without the client's read timeout, a
+/// quiet channel is not a broken connection.
+///
+/// When the connection breaks, the call that noticed returns the error and
+/// the next one reconnects following the client's reconnect policy, then
+/// subscribes again to every channel and pattern. The iterator instead ends
+/// after such an error.
+pub struct Subscriber<'a> {
+    client: &'a Client,
+    // opened on the first subscription, dropped when it breaks
+    connection: Option<Connection>,
+    channels: HashSet<Vec<u8>>,
+    patterns: HashSet<Vec<u8>>,
+    shard_channels: HashSet<Vec<u8>>,
+    // messages read while waiting for a confirmation
+    pending: VecDeque<Message>,
+    // the iterator stops after a connection error
+    finished: bool,
+}
+
+impl<'a> Subscriber<'a> {
+    pub fn new(client: &'a Client) -> Self {
+        Subscriber {
+            client,
+            connection: None,
+            channels: HashSet::new(),
+            patterns: HashSet::new(),
+            shard_channels: HashSet::new(),
+            pending: VecDeque::new(),
+            finished: false,
+        }
+    }
+
+    pub fn subscribe<K: AsRef<[u8]>>(&mut self, channels: &[K]) -> Result<(), RedashError> {
+        self.change(Kind::Channel, true, channels)
+    }
+
+    pub fn unsubscribe<K: AsRef<[u8]>>(&mut self, channels: &[K]) -> Result<(), RedashError> {
+        self.change(Kind::Channel, false, channels)
+    }
+
+    pub fn psubscribe<K: AsRef<[u8]>>(&mut self, patterns: &[K]) -> Result<(), RedashError> {
+        self.change(Kind::Pattern, true, patterns)
+    }
+
+    pub fn punsubscribe<K: AsRef<[u8]>>(&mut self, patterns: &[K]) -> Result<(), RedashError> {
+        self.change(Kind::Pattern, false, patterns)
+    }
+
+    pub fn ssubscribe<K: AsRef<[u8]>>(&mut self, channels: &[K]) -> Result<(), RedashError> {
+        self.change(Kind::Shard, true, channels)
+    }
+
+    pub fn sunsubscribe<K: AsRef<[u8]>>(&mut self, channels: &[K]) -> Result<(), RedashError> {
+        self.change(Kind::Shard, false, channels)
+    }
+
+    /// Number of active channel, pattern and shard channel subscriptions.
+    pub fn subscription_count(&self) -> usize {
+        self.channels.len() + self.patterns.len() + self.shard_channels.len()
+    }
+
+    /// Blocks until the next published message arrives.
+    pub fn next_message(&mut self) -> Result<Message, RedashError> {
+        if let Some(message) = self.pending.pop_front() {
+            return Ok(message);
+        }
+        loop {
+            if let Frame::Message(message) = self.read_frame()? {
+                return Ok(message);
+            }
+        }
+    }
+
+    /// Unsubscribes from every channel and pattern and closes the connection.
+    pub fn close(mut self) -> Result<(), RedashError> {
+        if self.connection.is_none() {
+            return Ok(());
+        }
+        for kind in [Kind::Channel, Kind::Pattern, Kind::Shard] {
+            let names = self.tracked(kind);
+            self.change(kind, false, &names)?;
+        }
+        Ok(())
+    }
+
+    fn tracked(&mut self, kind: Kind) -> Vec<Vec<u8>> {
+        self.names(kind).iter().cloned().collect()
+    }
+
+    /// The open connection, reconnecting and subscribing again to the
+    /// tracked names if it broke.
+    fn connection(&mut self) -> Result<&Connection, RedashError> {
+        if self.connection.is_none() {
+            let reconnecting = self.subscription_count() > 0;
+            let connection = if reconnecting {
+                self.client.reconnect()?
+            } else {
+                self.client.open()?
+            };
+            connection.set_read_timeout(None)?;
+            self.connection = Some(connection);
+            if reconnecting {
+                for kind in [Kind::Channel, Kind::Pattern, Kind::Shard] {
+                    let names = self.tracked(kind);
+                    self.change(kind, true, &names)?;
+                }
+            }
+        }
+        match &self.connection {
+            Some(connection) => Ok(connection),
+            None => Err(RedashError::OperationError(String::from("no_connection"))),
+        }
+    }
+
+    /// Forgets a connection that can no longer be used.
+    fn check<T>(&mut self, result: Result<T, RedashError>) -> Result<T, RedashError> {
+        if result.as_ref().is_err_and(RedashError::is_connection_error) {
+            self.connection = None;
+        }
+        result
+    }
+
+    fn names(&mut self, kind: Kind) -> &mut HashSet<Vec<u8>> {
+        match kind {
+            Kind::Channel => &mut self.channels,
+            Kind::Pattern => &mut self.patterns,
+            Kind::Shard => &mut self.shard_channels,
+        }
+    }
+
+    /// Sends a (un)subscribe command and waits for one confirmation per name.
+    fn change<K: AsRef<[u8]>>(
+        &mut self,
+        kind: Kind,
+        subscribe: bool,
+        names: &[K],
+    ) -> Result<(), RedashError> {
+        if names.is_empty() {
+            return Ok(());
+        }
+        let command = if subscribe {
+            kind.subscribe_command()
+        } else {
+            kind.unsubscribe_command()
+        };
+        let mut args: Vec<&[u8]> = vec![command.as_bytes()];
+        args.extend(names.iter().map(|name| name.as_ref()));
+        let sent = self.connection()?.send(&args);
+        self.check(sent)?;
+
+        let mut confirmations = 0;
+        while confirmations < names.len() {
+            match self.read_frame()? {
+                Frame::Message(message) => self.pending.push_back(message),
+                Frame::Confirmation => confirmations += 1,
+                Frame::Other => (),
+            }
+        }
+
+        let set = self.names(kind);
+        for name in names {
+            if subscribe {
+                set.insert(name.as_ref().to_vec());
+            } else {
+                set.remove(name.as_ref());
+            }
+        }
+        Ok(())
+    }
+
+    fn read_frame(&mut self) -> Result<Frame, RedashError> {
+        let data = self.connection()?.read();
+        let data = self.check(data)?;
+        let items = match data {
+            Data::Array(items) | Data::Push(items) => items,
+            _ => return Ok(Frame::Other),
+        };
+
+        let mut items = items.into_iter();
+        let kind = match items.next().and_then(Data::into_bytes) {
+            Some(kind) => kind.to_ascii_lowercase(),
+            None => return Ok(Frame::Other),
+        };
+        let mut next_bytes = || {
+            items
+                .next()
+                .and_then(Data::into_bytes)
+                .ok_or_else(|| RedashError::OperationError(String::from("invalid pubsub message")))
+        };
+
+        let frame = match &kind[..] {
+            b"message" => Frame::Message(Message::Message {
+                channel: next_bytes()?,
+                payload: next_bytes()?,
+            }),
+            b"pmessage" => Frame::Message(Message::PMessage {
+                pattern: next_bytes()?,
+                channel: next_bytes()?,
+                payload: next_bytes()?,
+            }),
+            b"smessage" => Frame::Message(Message::SMessage {
+                channel: next_bytes()?,
+                payload: next_bytes()?,
+            }),
+            b"subscribe" | b"unsubscribe" | b"psubscribe" | b"punsubscribe" | b"ssubscribe"
+            | b"sunsubscribe" => Frame::Confirmation,
+            _ => Frame::Other,
+        };
+        Ok(frame)
+    }
+}
+
+impl<'a> Iterator for Subscriber<'a> {
+    type Item = Result<Message, RedashError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.finished {
+            return None;
+        }
+        let message = self.next_message();
+        self.finished = message
+            .as_ref()
+            .is_err_and(RedashError::is_connection_error);
+        Some(message)
+    }
+}
+
+impl Client {
+    /// Creates a subscriber, which opens its own connection on the first
+    /// subscription.
+    pub fn subscriber(&self) -> Subscriber<'_> {
+        Subscriber::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{thread, time::Duration};
+
+    use super::*;
+    use crate::client::{
+        fake_server::FakeServer, options::ConnectionOptions, reconnect::ReconnectPolicy,
+    };
+
+    /// Publishes until a subscriber receives the message, going on after
+    /// the connection was broken by the server.
+    fn publish(client: &Client, channel: &str, message: &str) {
+        loop {
+            match client.send_args(&["PUBLISH", channel, message]) {
+                Ok(Data::Integer(0)) => (),
+                Ok(_) => return,
+                Err(err) => assert!(err.is_connection_error(), "{err}"),
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+    }
+
+    #[test]
+    fn test_subscriber_messages() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+
+        let mut subscriber = client.subscriber();
+        subscriber.subscribe(&["news"]).unwrap();
+        publish(&client, "news", "hello");
+        assert_eq!(
+            subscriber.next().unwrap().unwrap(),
+            Message::Message {
+                channel: b"news".to_vec(),
+                payload: b"hello".to_vec(),
+            }
+        );
+
+        subscriber.psubscribe(&["n*"]).unwrap();
+        assert_eq!(subscriber.subscription_count(), 2);
+        subscriber.unsubscribe(&["news"]).unwrap();
+        assert_eq!(subscriber.subscription_count(), 1);
+        publish(&client, "news", "bye");
+        assert_eq!(
+            subscriber.next_message().unwrap(),
+            Message::PMessage {
+                pattern: b"n*".to_vec(),
+                channel: b"news".to_vec(),
+                payload: b"bye".to_vec(),
+            }
+        );
+        subscriber.close().unwrap();
+        assert_eq!(
+            client.send_args(&["PUBLISH", "news", "unheard"]).unwrap(),
+            Data::Integer(0)
+        );
+    }
+
+    #[test]
+    fn test_subscriber_waits_past_the_read_timeout() {
+        let server = FakeServer::start().unwrap();
+        let client = Client::with_options(ConnectionOptions {
+            read_timeout: Some(Duration::from_millis(20)),
+            ..server.options()
+        });
+        client.connect().unwrap();
+
+        let mut subscriber = client.subscriber();
+        subscriber.subscribe(&["news"]).unwrap();
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                thread::sleep(Duration::from_millis(100));
+                publish(&client, "news", "late");
+            });
+            assert_eq!(
+                subscriber.next().unwrap().unwrap(),
+                Message::Message {
+                    channel: b"news".to_vec(),
+                    payload: b"late".to_vec(),
+                }
+            );
+        });
+    }
+
+    #[test]
+    fn test_subscriber_keeps_its_connection_and_resubscribes() {
+        let server = FakeServer::start().unwrap();
+        let client = Client::with_options(ConnectionOptions {
+            reconnect: ReconnectPolicy {
+                initial_delay: Duration::from_millis(1),
+                ..ReconnectPolicy::default()
+            },
+            ..server.options()
+        });
+        client.connect().unwrap();
+
+        let mut subscriber = client.subscriber();
+        subscriber.subscribe(&["news"]).unwrap();
+        // regular commands go over the client's own connection
+        client.set("k", "v").unwrap();
+        publish(&client, "news", "hello");
+        assert!(matches!(
+            subscriber.next(),
+            Some(Ok(Message::Message { .. }))
+        ));
+
+        server.disconnect_all();
+        assert!(subscriber
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .is_connection_error());
+        assert!(subscriber.next().is_none());
+
+        // the next read reconnects and subscribes again
+        thread::scope(|scope| {
+            scope.spawn(|| publish(&client, "news", "again"));
+            assert!(matches!(
+                subscriber.next_message(),
+                Ok(Message::Message { .. })
+            ));
+        });
+        let subscribes = server
+            .commands()
+            .iter()
+            .filter(|command| *command == "SUBSCRIBE news")
+            .count();
+        assert_eq!(subscribes, 2);
+    }
+}
@@ -0,0 +1,162 @@
+use std::{cmp::min, fmt::Display, sync::mpsc, thread, time::Duration};
+
+use super::{connection::Connection, errors::RedashError, lock, Client};
+
+/// How a client reconnects after its connection broke.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ReconnectPolicy {
+    /// Connection attempts before giving up, `0` disables reconnecting.
+    pub max_attempts: u32,
+    /// Delay after the first failed attempt, doubled after each one.
+    pub initial_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        ReconnectPolicy {
+            max_attempts: 5,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl ReconnectPolicy {
+    /// Never reconnects automatically.
+    pub fn disabled() -> Self {
+        ReconnectPolicy {
+            max_attempts: 0,
+            ..ReconnectPolicy::default()
+        }
+    }
+
+    /// Delay to wait after the given failed attempt, starting at 1.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2_u32.saturating_pow(attempt.saturating_sub(1));
+        min(self.initial_delay.saturating_mul(factor), self.max_delay)
+    }
+}
+
+/// Connection state changes reported to [`Client::reconnect_events`] receivers.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ReconnectEvent {
+    /// The connection broke and was dropped.
+    Disconnected {
+        reason: String,
+    },
+    Reconnecting {
+        attempt: u32,
+    },
+    /// A new connection is up, with authentication, client name and
+    /// selected database restored.
+    Reconnected {
+        attempt: u32,
+    },
+    /// Every attempt failed, the next command tries again.
+    ReconnectFailed {
+        reason: String,
+    },
+}
+
+impl Display for ReconnectEvent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ReconnectEvent::Disconnected { reason } => write!(f, "disconnected: {reason}"),
+            ReconnectEvent::Reconnecting { attempt } => {
+                write!(f, "reconnecting (attempt {attempt})")
+            }
+            ReconnectEvent::Reconnected { attempt } => {
+                write!(f, "reconnected after {attempt} attempt(s)")
+            }
+            ReconnectEvent::ReconnectFailed { reason } => write!(f, "reconnect failed: {reason}"),
+        }
+    }
+}
+
+impl Client {
+    /// Returns a receiver of the reconnect events raised from now on.
+    pub fn reconnect_events(&self) -> mpsc::Receiver<ReconnectEvent> {
+        let (sender, receiver) = mpsc::channel();
+        lock(&self.event_senders).push(sender);
+        receiver
+    }
+
+    pub(super) fn emit(&self, event: ReconnectEvent) {
+        // receivers that were dropped are forgotten
+        lock(&self.event_senders).retain(|sender| sender.send(event.clone()).is_ok());
+    }
+
+    /// Opens a new connection, retrying with exponential backoff.
+    pub(super) fn reconnect(&self) -> Result<Connection, RedashError> {
+        let policy = &self.options.reconnect;
+        let mut last_error = RedashError::OperationError(String::from("no_connection"));
+
+        for attempt in 1..=policy.max_attempts {
+            self.emit(ReconnectEvent::Reconnecting { attempt });
+            match self.open() {
+                Ok(connection) => {
+                    self.emit(ReconnectEvent::Reconnected { attempt });
+                    return Ok(connection);
+                }
+                Err(err) => last_error = err,
+            }
+            if attempt < policy.max_attempts {
+                thread::sleep(policy.delay(attempt));
+            }
+        }
+
+        if policy.max_attempts > 0 {
+            self.emit(ReconnectEvent::ReconnectFailed {
+                reason: last_error.to_string(),
+            });
+        }
+        Err(last_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::{fake_server::FakeServer, options::ConnectionOptions, parser::Data};
+
+    #[test]
+    fn test_backoff_delay() {
+        let policy = ReconnectPolicy {
+            max_attempts: 10,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(500),
+        };
+        assert_eq!(policy.delay(1), Duration::from_millis(100));
+        assert_eq!(policy.delay(2), Duration::from_millis(200));
+        assert_eq!(policy.delay(3), Duration::from_millis(400));
+        assert_eq!(policy.delay(4), Duration::from_millis(500));
+        assert_eq!(policy.delay(40), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn test_reconnect_replays_selected_database() {
+        let server = FakeServer::start().unwrap();
+        let client = Client::with_options(ConnectionOptions {
+            reconnect: ReconnectPolicy {
+                initial_delay: Duration::from_millis(1),
+                ..ReconnectPolicy::default()
+            },
+            ..server.options()
+        });
+        let events = client.reconnect_events();
+        client.connect().unwrap();
+
+        client.send_command("SELECT 3").unwrap();
+        server.disconnect_all();
+        assert!(client.send_command("PING").is_err());
+        assert_eq!(client.send_command("PING").unwrap(), Data::from("PONG"));
+
+        // the selected database is restored first on the new connection
+        assert_eq!(server.commands(), ["SELECT 3", "SELECT 3", "PING"]);
+        let events: Vec<ReconnectEvent> = events.try_iter().collect();
+        assert!(matches!(events[0], ReconnectEvent::Disconnected { .. }));
+        assert_eq!(events[1], ReconnectEvent::Reconnecting { attempt: 1 });
+        assert_eq!(events[2], ReconnectEvent::Reconnected { attempt: 1 });
+    }
+}
@@ -0,0 +1,233 @@
+use super::{
+    connection::Connection,
+    errors::RedashError,
+    options::{Address, ConnectionOptions, SentinelOptions},
+    parser::Data,
+    transport::Transport,
+};
+
+/// Asks each sentinel in turn for the master of the service and opens a
+/// connection to it with `open`, until one is confirmed by `ROLE`.
+/// Returns the connection and the master address.
+///
+/// Called again on every reconnect, so the new master is found after a
+/// failover.
+pub(crate) fn open_master(
+    options: &ConnectionOptions,
+    sentinel: &SentinelOptions,
+    open: impl Fn(&Address) -> Result<Connection, RedashError>,
+) -> Result<(Connection, Address), RedashError> {
+    let mut last_error = RedashError::OperationError(String::from("no sentinel configured"));
+    for address in &sentinel.sentinels {
+        let master = match master_address(options, sentinel, address) {
+            Ok(master) => master,
+            Err(err) => {
+                last_error = err;
+                continue;
+            }
+        };
+        // a sentinel may not have seen the failover yet
+        match open(&master).and_then(|connection| confirm_master(connection, &master)) {
+            Ok(connection) => return Ok((connection, master)),
+            Err(err) => last_error = err,
+        }
+    }
+    Err(last_error)
+}
+
+/// Master address reported by one sentinel, authenticating first when the
+/// sentinels require it.
+fn master_address(
+    options: &ConnectionOptions,
+    sentinels: &SentinelOptions,
+    sentinel: &Address,
+) -> Result<Address, RedashError> {
+    let master_name = &sentinels.master_name[..];
+    // sentinels share the TLS and timeout settings, not the credentials
+    let sentinel_options = ConnectionOptions {
+        address: sentinel.clone(),
+        username: sentinels.username.clone(),
+        password: sentinels.password.clone(),
+        tls: options.tls.clone(),
+        connect_timeout: options.connect_timeout,
+        read_timeout: options.read_timeout,
+        write_timeout: options.write_timeout,
+        parser_limits: options.parser_limits,
+        ..ConnectionOptions::default()
+    };
+    let handshake = sentinel_options.handshake()?;
+    let connection = Connection::with_limits(
+        Transport::connect(&sentinel_options)?,
+        sentinel_options.parser_limits,
+    );
+    for command in handshake {
+        connection.request(&command)?;
+    }
+
+    match connection.request(&["SENTINEL", "get-master-addr-by-name", master_name])? {
+        Data::Array(items) if items.len() == 2 => {
+            let host = items[0].as_str();
+            let port = items[1].as_str().and_then(|port| port.parse().ok());
+            match (host, port) {
+                (Some(host), Some(port)) => Ok(Address::tcp(host, port)),
+                _ => Err(RedashError::OperationError(format!(
+                    "invalid master address from sentinel {sentinel}"
+                ))),
+            }
+        }
+        Data::Null => Err(RedashError::OperationError(format!(
+            "sentinel {sentinel} does not know master {master_name}"
+        ))),
+        data => Err(RedashError::OperationError(format!(
+            "unexpected reply from sentinel {sentinel}: {data}"
+        ))),
+    }
+}
+
+fn confirm_master(connection: Connection, address: &Address) -> Result<Connection, RedashError> {
+    let role = match connection.request(&["ROLE"])? {
+        Data::Array(items) | Data::Push(items) => items
+            .first()
+            .and_then(|role| role.as_str().map(String::from)),
+        _ => None,
+    };
+    match role.as_deref() {
+        Some("master") => Ok(connection),
+        role => Err(RedashError::OperationError(format!(
+            "{address} is not a master, its role is {}",
+            role.unwrap_or("unknown")
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicBool, AtomicU16, Ordering},
+        Arc,
+    };
+
+    use super::*;
+    use crate::client::{
+        fake_server::{FakeServer, Scripted},
+        Client,
+    };
+
+    /// A data node answering `ROLE` and refusing writes once demoted.
+    fn data_server(is_master: Arc<AtomicBool>) -> FakeServer {
+        let server = FakeServer::start().unwrap();
+        let role = is_master.clone();
+        server.handle("ROLE", move |_| {
+            let role = match role.load(Ordering::SeqCst) {
+                true => "master",
+                false => "slave",
+            };
+            Scripted::Reply(Data::Array(vec![Data::from(role)]))
+        });
+        server.handle("SET", move |_| {
+            Scripted::Reply(match is_master.load(Ordering::SeqCst) {
+                true => Data::from("OK"),
+                false => Data::Error(String::from(
+                    "READONLY You can't write against a read only replica.",
+                )),
+            })
+        });
+        server
+    }
+
+    /// A sentinel reporting the node on port `master` as the master of
+    /// `mymaster`.
+    fn sentinel_server(master: Arc<AtomicU16>) -> FakeServer {
+        let server = FakeServer::start().unwrap();
+        server.handle("SENTINEL", move |args| {
+            Scripted::Reply(match &args[2..] {
+                [name] if name == b"mymaster" => Data::Array(vec![
+                    Data::from("127.0.0.1"),
+                    Data::from(master.load(Ordering::SeqCst).to_string()),
+                ]),
+                _ => Data::Null,
+            })
+        });
+        server
+    }
+
+    fn sentinel_options(sentinels: &[&FakeServer], master_name: &str) -> ConnectionOptions {
+        ConnectionOptions {
+            sentinel: Some(SentinelOptions {
+                sentinels: sentinels
+                    .iter()
+                    .map(|sentinel| sentinel.address().clone())
+                    .collect(),
+                master_name: String::from(master_name),
+                username: None,
+                password: None,
+            }),
+            ..ConnectionOptions::default()
+        }
+    }
+
+    #[test]
+    fn test_unknown_master_name() {
+        let sentinel = sentinel_server(Arc::new(AtomicU16::new(0)));
+        let client = Client::with_options(sentinel_options(&[&sentinel], "other"));
+        let err = client.connect().unwrap_err();
+        assert!(err.to_string().contains("does not know master other"));
+    }
+
+    #[test]
+    fn test_replica_reported_as_master_is_skipped() {
+        let replica = data_server(Arc::new(AtomicBool::new(false)));
+        let master = data_server(Arc::new(AtomicBool::new(true)));
+        let stale = sentinel_server(Arc::new(AtomicU16::new(replica.port().unwrap())));
+        let current = sentinel_server(Arc::new(AtomicU16::new(master.port().unwrap())));
+
+        let client = Client::with_options(sentinel_options(&[&stale, &current], "mymaster"));
+        client.connect().unwrap();
+        assert_eq!(client.server_address(), Some(master.address().clone()));
+    }
+
+    #[test]
+    fn test_master_is_rediscovered_after_failover() {
+        let old_is_master = Arc::new(AtomicBool::new(true));
+        let old_master = data_server(old_is_master.clone());
+        let master = Arc::new(AtomicU16::new(old_master.port().unwrap()));
+        let sentinel = sentinel_server(master.clone());
+
+        let client = Client::with_options(sentinel_options(&[&sentinel], "mymaster"));
+        client.connect().unwrap();
+
+        // the old master is demoted and a replica promoted
+        let new_master = data_server(Arc::new(AtomicBool::new(true)));
+        old_is_master.store(false, Ordering::SeqCst);
+        master.store(new_master.port().unwrap(), Ordering::SeqCst);
+
+        let err = client.send_command("SET a 1").unwrap_err();
+        assert!(err.reply().unwrap().is_readonly());
+        assert_eq!(client.send_command("SET a 1").unwrap(), Data::from("OK"));
+        assert_eq!(client.server_address(), Some(new_master.address().clone()));
+    }
+
+    #[test]
+    fn test_sentinel_authentication() {
+        let master = data_server(Arc::new(AtomicBool::new(true)));
+        let sentinel = sentinel_server(Arc::new(AtomicU16::new(master.port().unwrap())));
+        sentinel.require_password("watcher-secret");
+
+        let mut options = sentinel_options(&[&sentinel], "mymaster");
+        options.sentinel.as_mut().unwrap().password = Some(String::from("wrong"));
+        let client = Client::with_options(options.clone());
+        assert!(matches!(client.connect(), Err(RedashError::AuthFailed(_))));
+
+        options.sentinel.as_mut().unwrap().password = Some(String::from("watcher-secret"));
+        let client = Client::with_options(options);
+        client.connect().unwrap();
+        assert_eq!(client.server_address(), Some(master.address().clone()));
+        assert_eq!(
+            sentinel.commands()[1..],
+            [
+                "AUTH watcher-secret",
+                "SENTINEL get-master-addr-by-name mymaster"
+            ]
+        );
+    }
+}
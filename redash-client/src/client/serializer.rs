@@ -0,0 +1,276 @@
+//! Encoding of [`Data`] back into RESP, the counterpart of the
+//! [`parser`](super::parser).
+
+use std::fmt::Display;
+
+use bytes::BufMut;
+
+use super::{parser::Data, ProtocolVersion};
+
+impl Data {
+    /// Appends the RESP encoding of the reply to `buf`.
+    ///
+    /// Strings are always written as bulk strings. For RESP2, RESP3 types
+    /// are downgraded the way the server does: null becomes a null bulk
+    /// string, doubles, big numbers and verbatim strings become bulk strings,
+    /// booleans become `1` or `0`, maps become flat arrays of keys and
+    /// values, sets and pushes become arrays and attributes are dropped.
+    ///
+    /// Values that cannot be written as their own type fall back to a bulk
+    /// string: big numbers spanning several lines, and verbatim strings
+    /// whose format is not three bytes without a `:`. Errors spanning
+    /// several lines are blob errors over RESP3 and joined into one line
+    /// over RESP2.
+    pub fn encode<B: BufMut>(&self, protocol: ProtocolVersion, buf: &mut B) {
+        let resp3 = protocol == ProtocolVersion::Resp3;
+        match self {
+            Data::String(bytes) => bulk(buf, b'$', bytes),
+            Data::Integer(i) => line(buf, b':', i),
+            Data::Array(items) => aggregate(buf, protocol, b'*', items),
+            Data::Null if resp3 => buf.put_slice(b"_\r\n"),
+            Data::Null => buf.put_slice(b"$-1\r\n"),
+            Data::Error(err) if resp3 && err.contains(['\r', '\n']) => {
+                bulk(buf, b'!', err.as_bytes())
+            }
+            Data::Error(err) => line(buf, b'-', err.replace(['\r', '\n'], " ")),
+            Data::Double(d) => {
+                let text = match *d {
+                    f64::INFINITY => String::from("inf"),
+                    f64::NEG_INFINITY => String::from("-inf"),
+                    d if d.is_nan() => String::from("nan"),
+                    d => d.to_string(),
+                };
+                if resp3 {
+                    line(buf, b',', text)
+                } else {
+                    bulk(buf, b'$', text.as_bytes())
+                }
+            }
+            Data::Boolean(b) if resp3 => line(buf, b'#', if *b { "t" } else { "f" }),
+            Data::Boolean(b) => line(buf, b':', u8::from(*b)),
+            Data::BigNumber(n) if resp3 && !n.contains(['\r', '\n']) => line(buf, b'(', n),
+            Data::BigNumber(n) => bulk(buf, b'$', n.as_bytes()),
+            Data::Verbatim { format, text } if resp3 && is_verbatim_format(format) => {
+                line(buf, b'=', format.len() + 1 + text.len());
+                buf.put_slice(format.as_bytes());
+                buf.put_u8(b':');
+                buf.put_slice(text.as_bytes());
+                buf.put_slice(b"\r\n");
+            }
+            Data::Verbatim { text, .. } => bulk(buf, b'$', text.as_bytes()),
+            Data::Map(entries) => pairs(buf, protocol, b'%', entries),
+            Data::Set(items) => aggregate(buf, protocol, b'~', items),
+            Data::Push(items) => aggregate(buf, protocol, b'>', items),
+            Data::Attribute { attributes, data } => {
+                if resp3 {
+                    pairs(buf, protocol, b'|', attributes);
+                }
+                data.encode(protocol, buf);
+            }
+        }
+    }
+
+    /// Encodes the reply into a new buffer, see [`Data::encode`].
+    pub fn to_resp(&self, protocol: ProtocolVersion) -> Vec<u8> {
+        let mut buf = Vec::new();
+        self.encode(protocol, &mut buf);
+        buf
+    }
+}
+
+/// The format of a verbatim string is read up to the first `:` and must
+/// be three bytes long.
+fn is_verbatim_format(format: &str) -> bool {
+    format.len() == 3 && !format.contains(':')
+}
+
+fn line<B: BufMut>(buf: &mut B, type_indicator: u8, value: impl Display) {
+    buf.put_u8(type_indicator);
+    buf.put_slice(value.to_string().as_bytes());
+    buf.put_slice(b"\r\n");
+}
+
+fn bulk<B: BufMut>(buf: &mut B, type_indicator: u8, payload: &[u8]) {
+    line(buf, type_indicator, payload.len());
+    buf.put_slice(payload);
+    buf.put_slice(b"\r\n");
+}
+
+fn aggregate<B: BufMut>(
+    buf: &mut B,
+    protocol: ProtocolVersion,
+    type_indicator: u8,
+    items: &[Data],
+) {
+    let type_indicator = match protocol {
+        ProtocolVersion::Resp3 => type_indicator,
+        ProtocolVersion::Resp2 => b'*',
+    };
+    line(buf, type_indicator, items.len());
+    for item in items {
+        item.encode(protocol, buf);
+    }
+}
+
+fn pairs<B: BufMut>(
+    buf: &mut B,
+    protocol: ProtocolVersion,
+    type_indicator: u8,
+    entries: &[(Data, Data)],
+) {
+    match protocol {
+        ProtocolVersion::Resp3 => line(buf, type_indicator, entries.len()),
+        ProtocolVersion::Resp2 => line(buf, b'*', entries.len() * 2),
+    }
+    for (key, value) in entries {
+        key.encode(protocol, buf);
+        value.encode(protocol, buf);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use proptest::{collection::vec, prelude::*};
+
+    use super::*;
+    use crate::client::parser::{decode, Decoded};
+
+    fn scalar() -> impl Strategy<Value = Data> {
+        prop_oneof![
+            any::<Vec<u8>>().prop_map(Data::from),
+            any::<i64>().prop_map(Data::Integer),
+            Just(Data::Null),
+            any::<String>().prop_map(Data::Error),
+            // NaN never equals itself, it is checked on its own
+            any::<f64>()
+                .prop_filter("not NaN", |d| !d.is_nan())
+                .prop_map(Data::Double),
+            any::<bool>().prop_map(Data::Boolean),
+            "-?[0-9]{1,50}".prop_map(Data::BigNumber),
+            ("[a-z]{3}", any::<String>())
+                .prop_map(|(format, text)| Data::Verbatim { format, text }),
+        ]
+    }
+
+    fn data() -> impl Strategy<Value = Data> {
+        scalar().prop_recursive(4, 64, 8, |inner| {
+            let entries = vec((inner.clone(), inner.clone()), 0..4);
+            prop_oneof![
+                vec(inner.clone(), 0..8).prop_map(Data::Array),
+                vec(inner.clone(), 0..8).prop_map(Data::Set),
+                vec(inner.clone(), 0..8).prop_map(Data::Push),
+                entries.clone().prop_map(Data::Map),
+                (entries, inner).prop_map(|(attributes, data)| Data::Attribute {
+                    attributes,
+                    data: Box::new(data),
+                }),
+            ]
+        })
+    }
+
+    /// Like [`scalar`], with text in the types written on a single line or
+    /// with a fixed size part, which cannot always be encoded as such.
+    fn any_scalar() -> impl Strategy<Value = Data> {
+        prop_oneof![
+            scalar(),
+            any::<String>().prop_map(Data::Error),
+            any::<String>().prop_map(Data::BigNumber),
+            (any::<String>(), any::<String>())
+                .prop_map(|(format, text)| Data::Verbatim { format, text }),
+        ]
+    }
+
+    proptest! {
+        #[test]
+        fn test_any_value_encodes_to_one_frame(
+            data in any_scalar().prop_recursive(2, 16, 4, |inner| {
+                vec(inner, 0..4).prop_map(Data::Array)
+            })
+        ) {
+            for protocol in [ProtocolVersion::Resp2, ProtocolVersion::Resp3] {
+                let encoded = data.to_resp(protocol);
+                prop_assert!(matches!(
+                    decode(&encoded),
+                    Ok(Decoded::Frame(_, length)) if length == encoded.len()
+                ));
+            }
+        }
+
+        #[test]
+        fn test_resp3_round_trip(data in data()) {
+            let encoded = data.to_resp(ProtocolVersion::Resp3);
+            prop_assert_eq!(decode(&encoded).unwrap(), Decoded::Frame(data, encoded.len()));
+        }
+
+        #[test]
+        fn test_resp2_encoding_is_stable(data in data()) {
+            let encoded = data.to_resp(ProtocolVersion::Resp2);
+            let decoded = match decode(&encoded).unwrap() {
+                Decoded::Frame(decoded, length) if length == encoded.len() => decoded,
+                other => panic!("unexpected result: {other:?}"),
+            };
+            // only RESP2 types are left, which encode the same way again
+            prop_assert_eq!(decoded.to_resp(ProtocolVersion::Resp2), encoded);
+        }
+    }
+
+    #[test]
+    fn test_encode_map() {
+        let map = Data::Map(vec![
+            (Data::from("a"), Data::Boolean(true)),
+            (Data::from("b"), Data::Null),
+        ]);
+        assert_eq!(
+            map.to_resp(ProtocolVersion::Resp3),
+            b"%2\r\n$1\r\na\r\n#t\r\n$1\r\nb\r\n_\r\n"
+        );
+        assert_eq!(
+            map.to_resp(ProtocolVersion::Resp2),
+            b"*4\r\n$1\r\na\r\n:1\r\n$1\r\nb\r\n$-1\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_errors_and_doubles() {
+        let err = Data::Error(String::from("ERR bad\r\nline"));
+        assert_eq!(
+            err.to_resp(ProtocolVersion::Resp3),
+            b"!13\r\nERR bad\r\nline\r\n"
+        );
+        assert_eq!(err.to_resp(ProtocolVersion::Resp2), b"-ERR bad  line\r\n");
+
+        let nan = Data::Double(f64::NAN).to_resp(ProtocolVersion::Resp3);
+        assert_eq!(nan, b",nan\r\n");
+        assert!(matches!(
+            decode(&nan).unwrap(),
+            Decoded::Frame(Data::Double(d), 6) if d.is_nan()
+        ));
+        assert_eq!(
+            Data::Double(-1.5).to_resp(ProtocolVersion::Resp2),
+            b"$4\r\n-1.5\r\n"
+        );
+    }
+
+    #[test]
+    fn test_encode_invalid_big_numbers_and_formats() {
+        let number = Data::BigNumber(String::from("12\r\n:3"));
+        assert_eq!(
+            number.to_resp(ProtocolVersion::Resp3),
+            b"$6\r\n12\r\n:3\r\n"
+        );
+
+        let text = |format: &str| Data::Verbatim {
+            format: String::from(format),
+            text: String::from("hi"),
+        };
+        assert_eq!(
+            text("txt").to_resp(ProtocolVersion::Resp3),
+            b"=6\r\ntxt:hi\r\n"
+        );
+        assert_eq!(
+            text("text").to_resp(ProtocolVersion::Resp3),
+            b"$2\r\nhi\r\n"
+        );
+        assert_eq!(text("a:b").to_resp(ProtocolVersion::Resp3), b"$2\r\nhi\r\n");
+    }
+}
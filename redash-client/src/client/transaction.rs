@@ -0,0 +1,326 @@
+use super::{
+    command, connection::Connection, errors::RedashError, parser::Data, pipeline::Pipeline, Client,
+};
+
+/// Attempts of [`Client::transaction_with_retry`] before it gives up on
+/// keys that keep changing.
+pub const MAX_TRANSACTION_ATTEMPTS: usize = 16;
+
+/// Commands queued between `MULTI` and `EXEC`.
+///
+/// Nothing is sent until [`Transaction::execute`], which writes `MULTI`, the
+/// queued commands and `EXEC` in a single round trip.
+pub struct Transaction<'a> {
+    // starts with MULTI, EXEC is appended when executing
+    pipeline: Pipeline<'a>,
+}
+
+/// Replies of an executed transaction.
+#[derive(Debug)]
+pub struct TransactionResult {
+    /// Acknowledgement of each queued command, `QUEUED` unless the server
+    /// rejected the command.
+    pub queued: Vec<Result<Data, RedashError>>,
+    pub outcome: TransactionOutcome,
+}
+
+#[derive(Debug)]
+pub enum TransactionOutcome {
+    /// `EXEC` ran the commands, one reply per queued command.
+    Committed(Vec<Result<Data, RedashError>>),
+    /// `EXEC` returned a null reply because a `WATCH`ed key changed.
+    WatchConflict,
+    /// `EXEC` failed, e.g. `EXECABORT` after a command was rejected while
+    /// queueing. The server has already discarded the transaction.
+    Aborted(RedashError),
+}
+
+impl<'a> Transaction<'a> {
+    pub fn new(client: &'a Client) -> Self {
+        let mut pipeline = Pipeline::new(client);
+        pipeline.args(&["MULTI"]);
+        Transaction { pipeline }
+    }
+
+    /// Queues a `redis-cli` style command line.
+    pub fn command(&mut self, command: &str) -> Result<&mut Self, RedashError> {
+        self.pipeline.command(command)?;
+        Ok(self)
+    }
+
+    /// Queues already split arguments.
+    pub fn args<A: AsRef<[u8]>>(&mut self, args: &[A]) -> &mut Self {
+        self.pipeline.args(args);
+        self
+    }
+
+    /// Drops the queued commands without sending them and releases any
+    /// `WATCH`ed keys.
+    pub fn discard(self) -> Result<(), RedashError> {
+        self.pipeline.client().unwatch()
+    }
+
+    pub fn execute(&self) -> Result<TransactionResult, RedashError> {
+        let replies = self.with_exec().execute()?;
+        Transaction::result(replies)
+    }
+
+    /// Executes on a connection the caller already holds, e.g. the one
+    /// the keys were `WATCH`ed on.
+    fn execute_on(&self, connection: &Connection) -> Result<TransactionResult, RedashError> {
+        let replies = self.with_exec().execute_on(connection)?;
+        Transaction::result(replies)
+    }
+
+    fn with_exec(&self) -> Pipeline<'a> {
+        let mut pipeline = self.pipeline.clone();
+        pipeline.args(&["EXEC"]);
+        pipeline
+    }
+
+    fn result(
+        mut replies: Vec<Result<Data, RedashError>>,
+    ) -> Result<TransactionResult, RedashError> {
+        let exec = replies
+            .pop()
+            .ok_or_else(|| RedashError::OperationError(String::from("missing EXEC reply")))?;
+        let mut replies = replies.into_iter();
+        if let Some(Err(err)) = replies.next() {
+            // MULTI itself failed, e.g. when nested in another transaction
+            return Err(err);
+        }
+        let queued: Vec<Result<Data, RedashError>> = replies.collect();
+
+        let outcome = match exec {
+            Ok(Data::Array(results)) => TransactionOutcome::Committed(
+                results
+                    .into_iter()
+                    .map(|result| match result {
+                        Data::Error(err) => Err(RedashError::from_reply(&err)),
+                        data => Ok(data),
+                    })
+                    .collect(),
+            ),
+            Ok(Data::Null) => TransactionOutcome::WatchConflict,
+            Ok(data) => TransactionOutcome::Aborted(RedashError::OperationError(format!(
+                "unexpected EXEC reply: {data}"
+            ))),
+            Err(err) => TransactionOutcome::Aborted(err),
+        };
+
+        Ok(TransactionResult { queued, outcome })
+    }
+}
+
+/// Commands sent on the connection held for a whole
+/// [`Client::transaction_with_retry`] attempt, so that no other thread's
+/// command runs between `WATCH` and `EXEC`.
+pub struct Session<'a> {
+    connection: &'a Connection,
+}
+
+impl Session<'_> {
+    /// Tokenizes a `redis-cli` style command line and sends it.
+    pub fn send_command(&self, command: &str) -> Result<Data, RedashError> {
+        let args = command::tokenize(command)?;
+        self.send_args(&args)
+    }
+
+    /// Sends already split arguments, each one as a binary safe bulk string.
+    pub fn send_args<A: AsRef<[u8]>>(&self, args: &[A]) -> Result<Data, RedashError> {
+        if args.is_empty() {
+            return Err(RedashError::OperationError(String::from("empty command")));
+        }
+        self.connection.request(args)
+    }
+}
+
+impl Client {
+    /// Starts a `MULTI`/`EXEC` transaction.
+    pub fn transaction(&self) -> Transaction<'_> {
+        Transaction::new(self)
+    }
+
+    /// Marks keys to be watched for conditional execution of the next transaction.
+    pub fn watch<K: AsRef<[u8]>>(&self, keys: &[K]) -> Result<(), RedashError> {
+        self.send_args(&watch_args(keys))?;
+        Ok(())
+    }
+
+    pub fn unwatch(&self) -> Result<(), RedashError> {
+        self.send_args(&["UNWATCH"])?;
+        Ok(())
+    }
+
+    /// Runs an optimistic-locking check-and-set: `keys` are `WATCH`ed, then
+    /// `f` may read them through the session and queue commands. The whole
+    /// sequence is retried until `EXEC` is not aborted by a watched key change,
+    /// at most [`MAX_TRANSACTION_ATTEMPTS`] times before failing with
+    /// [`RedashError::WatchConflict`].
+    ///
+    /// The connection stays locked from `WATCH` to `EXEC`, commands from
+    /// other threads wait until the transaction is done. Calling the client
+    /// itself from `f` would deadlock, use the session instead.
+    pub fn transaction_with_retry<K, F>(
+        &self,
+        keys: &[K],
+        mut f: F,
+    ) -> Result<Vec<Result<Data, RedashError>>, RedashError>
+    where
+        K: AsRef<[u8]>,
+        F: FnMut(&Session, &mut Transaction) -> Result<(), RedashError>,
+    {
+        self.with_connection(|connection| {
+            for _ in 0..MAX_TRANSACTION_ATTEMPTS {
+                connection.request(&watch_args(keys))?;
+                let mut transaction = self.transaction();
+                if let Err(err) = f(&Session { connection }, &mut transaction) {
+                    if !err.is_connection_error() {
+                        connection.request(&["UNWATCH"])?;
+                    }
+                    return Err(err);
+                }
+
+                match transaction.execute_on(connection)?.outcome {
+                    TransactionOutcome::Committed(results) => return Ok(results),
+                    TransactionOutcome::WatchConflict => continue,
+                    TransactionOutcome::Aborted(err) => return Err(err),
+                }
+            }
+            Err(RedashError::WatchConflict {
+                attempts: MAX_TRANSACTION_ATTEMPTS,
+            })
+        })
+    }
+}
+
+fn watch_args<K: AsRef<[u8]>>(keys: &[K]) -> Vec<&[u8]> {
+    let mut args: Vec<&[u8]> = vec![b"WATCH"];
+    args.extend(keys.iter().map(|key| key.as_ref()));
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, thread};
+
+    use super::*;
+    use crate::client::fake_server::FakeServer;
+
+    #[test]
+    fn test_transaction_committed() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        let mut transaction = client.transaction();
+        transaction
+            .args(&["SET", "a", "1"])
+            .args(&["LPUSH", "a", "x"]);
+        let result = transaction.execute().unwrap();
+
+        assert_eq!(result.queued.len(), 2);
+        assert_eq!(result.queued[0].as_ref().unwrap(), &Data::from("QUEUED"));
+        match result.outcome {
+            TransactionOutcome::Committed(results) => {
+                assert_eq!(results[0].as_ref().unwrap(), &Data::from("OK"));
+                assert!(results[1].is_err());
+            }
+            outcome => panic!("unexpected outcome: {outcome:?}"),
+        }
+    }
+
+    #[test]
+    fn test_transaction_with_retry_on_watch_conflict() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        let other = server.connect().unwrap();
+        client.send_args(&["SET", "counter", "1"]).unwrap();
+
+        let mut attempts = 0;
+        let results = client
+            .transaction_with_retry(&["counter"], |session, transaction| {
+                attempts += 1;
+                if attempts == 1 {
+                    other.send_args(&["INCR", "counter"]).unwrap();
+                }
+                let current = session.send_args(&["GET", "counter"])?;
+                let next = current.as_str().unwrap().parse::<i64>().unwrap() + 1;
+                transaction.args(&["SET", "counter", &next.to_string()]);
+                Ok(())
+            })
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(results.len(), 1);
+        assert_eq!(
+            client.send_args(&["GET", "counter"]).unwrap(),
+            Data::from("3")
+        );
+    }
+
+    #[test]
+    fn test_transaction_with_retry_gives_up() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        let other = server.connect().unwrap();
+
+        let mut attempts = 0;
+        let err = client
+            .transaction_with_retry(&["counter"], |_, transaction| {
+                attempts += 1;
+                other.send_args(&["INCR", "counter"]).unwrap();
+                transaction.args(&["SET", "counter", "0"]);
+                Ok(())
+            })
+            .unwrap_err();
+
+        assert!(matches!(
+            err,
+            RedashError::WatchConflict {
+                attempts: MAX_TRANSACTION_ATTEMPTS
+            }
+        ));
+        assert_eq!(attempts, MAX_TRANSACTION_ATTEMPTS);
+        assert_eq!(
+            client.send_args(&["GET", "counter"]).unwrap(),
+            Data::from(MAX_TRANSACTION_ATTEMPTS.to_string())
+        );
+    }
+
+    #[test]
+    fn test_transaction_with_retry_holds_the_connection() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        client.send_args(&["SET", "counter", "1"]).unwrap();
+
+        thread::scope(|scope| {
+            client
+                .transaction_with_retry(&["counter"], |session, transaction| {
+                    // waits for the connection until EXEC is done
+                    let (started, start) = mpsc::channel();
+                    let client = &client;
+                    scope.spawn(move || {
+                        started.send(()).unwrap();
+                        client.send_args(&["SET", "counter", "10"]).unwrap()
+                    });
+                    start.recv().unwrap();
+                    let current = session.send_args(&["GET", "counter"])?;
+                    let next = current.as_str().unwrap().parse::<i64>().unwrap() + 1;
+                    transaction.args(&["SET", "counter", &next.to_string()]);
+                    Ok(())
+                })
+                .unwrap();
+        });
+
+        assert_eq!(
+            server.commands()[1..],
+            [
+                "WATCH counter",
+                "GET counter",
+                "MULTI",
+                "SET counter 2",
+                "EXEC",
+                "SET counter 10",
+            ]
+        );
+    }
+}
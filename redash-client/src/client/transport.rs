@@ -0,0 +1,360 @@
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::{
+    fs,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+use native_tls::{Certificate, Identity, TlsConnector, TlsStream};
+
+use super::{
+    errors::RedashError,
+    options::{Address, ConnectionOptions, TlsOptions},
+};
+
+/// The byte stream a connection talks over.
+pub enum Transport {
+    Tcp(TcpStream),
+    #[cfg(unix)]
+    Unix(UnixStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl Transport {
+    /// Opens the stream described by the connection options.
+    pub fn connect(options: &ConnectionOptions) -> Result<Self, RedashError> {
+        match (&options.address, &options.tls) {
+            (Address::Tcp { host, port }, tls) => {
+                let stream = tcp_connect(host, *port, options.connect_timeout)
+                    .map_err(RedashError::IOError)?;
+                stream
+                    .set_read_timeout(options.read_timeout)
+                    .and_then(|_| stream.set_write_timeout(options.write_timeout))
+                    .map_err(RedashError::IOError)?;
+                match tls {
+                    None => Ok(Transport::Tcp(stream)),
+                    Some(tls) => Transport::tls(stream, host, tls),
+                }
+            }
+            (Address::Unix(_), Some(_)) => Err(RedashError::OperationError(String::from(
+                "TLS is not supported over unix sockets",
+            ))),
+            #[cfg(unix)]
+            (Address::Unix(path), None) => {
+                let stream = UnixStream::connect(path).map_err(RedashError::IOError)?;
+                stream
+                    .set_read_timeout(options.read_timeout)
+                    .and_then(|_| stream.set_write_timeout(options.write_timeout))
+                    .map_err(RedashError::IOError)?;
+                Ok(Transport::Unix(stream))
+            }
+            #[cfg(not(unix))]
+            (Address::Unix(_), None) => Err(RedashError::OperationError(String::from(
+                "unix sockets are not supported on this platform",
+            ))),
+        }
+    }
+
+    /// Changes how long a read waits before failing, `None` to wait forever.
+    pub fn set_read_timeout(&self, timeout: Option<Duration>) -> io::Result<()> {
+        match self {
+            Transport::Tcp(stream) => stream.set_read_timeout(timeout),
+            #[cfg(unix)]
+            Transport::Unix(stream) => stream.set_read_timeout(timeout),
+            Transport::Tls(stream) => stream.get_ref().set_read_timeout(timeout),
+        }
+    }
+
+    fn tls(stream: TcpStream, host: &str, tls: &TlsOptions) -> Result<Self, RedashError> {
+        let connector = tls_connector(tls)?;
+        let domain = tls.sni.as_deref().unwrap_or(host);
+        match connector.connect(domain, stream) {
+            Ok(stream) => Ok(Transport::Tls(Box::new(stream))),
+            Err(err) => Err(RedashError::UnknownError(Box::new(err))),
+        }
+    }
+}
+
+/// Connects to the first reachable address the host resolves to.
+fn tcp_connect(host: &str, port: u16, timeout: Option<Duration>) -> io::Result<TcpStream> {
+    let timeout = match timeout {
+        Some(timeout) => timeout,
+        None => return TcpStream::connect((host, port)),
+    };
+
+    let mut last_error = io::Error::new(io::ErrorKind::NotFound, "host did not resolve");
+    for addr in (host, port).to_socket_addrs()? {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return Ok(stream),
+            Err(err) => last_error = err,
+        }
+    }
+    Err(last_error)
+}
+
+pub(crate) fn tls_connector(tls: &TlsOptions) -> Result<TlsConnector, RedashError> {
+    let mut builder = TlsConnector::builder();
+
+    if let Some(path) = &tls.ca_cert {
+        let pem = fs::read(path).map_err(RedashError::IOError)?;
+        let certificate =
+            Certificate::from_pem(&pem).map_err(|err| RedashError::UnknownError(Box::new(err)))?;
+        builder.add_root_certificate(certificate);
+    }
+
+    match (&tls.cert, &tls.key) {
+        (Some(cert), Some(key)) => {
+            let cert = fs::read(cert).map_err(RedashError::IOError)?;
+            let key = fs::read(key).map_err(RedashError::IOError)?;
+            let identity = Identity::from_pkcs8(&cert, &key)
+                .map_err(|err| RedashError::UnknownError(Box::new(err)))?;
+            builder.identity(identity);
+        }
+        (None, None) => (),
+        _ => {
+            return Err(RedashError::OperationError(String::from(
+                "a client certificate and key must be given together",
+            )))
+        }
+    }
+
+    if tls.insecure {
+        builder
+            .danger_accept_invalid_certs(true)
+            .danger_accept_invalid_hostnames(true);
+    }
+
+    builder
+        .build()
+        .map_err(|err| RedashError::UnknownError(Box::new(err)))
+}
+
+impl From<TcpStream> for Transport {
+    fn from(stream: TcpStream) -> Self {
+        Transport::Tcp(stream)
+    }
+}
+
+#[cfg(unix)]
+impl From<UnixStream> for Transport {
+    fn from(stream: UnixStream) -> Self {
+        Transport::Unix(stream)
+    }
+}
+
+impl Read for Transport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Transport::Tcp(stream) => stream.read(buf),
+            #[cfg(unix)]
+            Transport::Unix(stream) => stream.read(buf),
+            Transport::Tls(stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Transport {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Transport::Tcp(stream) => stream.write(buf),
+            #[cfg(unix)]
+            Transport::Unix(stream) => stream.write(buf),
+            Transport::Tls(stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Transport::Tcp(stream) => stream.flush(),
+            #[cfg(unix)]
+            Transport::Unix(stream) => stream.flush(),
+            Transport::Tls(stream) => stream.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::TcpListener, path::PathBuf, sync::mpsc, thread};
+
+    use native_tls::TlsAcceptor;
+    use openssl::{
+        nid::Nid,
+        ssl::{SslAcceptor, SslFiletype, SslMethod, SslVerifyMode},
+    };
+
+    use super::*;
+    use crate::client::{connection::Connection, parser::Data};
+
+    fn fixture(name: &str) -> PathBuf {
+        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+            .join("fixtures/tls")
+            .join(name)
+    }
+
+    /// Serves a single `+PONG` reply over TLS.
+    fn tls_server() -> u16 {
+        let cert = fs::read(fixture("server.crt")).unwrap();
+        let key = fs::read(fixture("server.key")).unwrap();
+        let acceptor = TlsAcceptor::new(Identity::from_pkcs8(&cert, &key).unwrap()).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            if let Ok(mut stream) = acceptor.accept(stream) {
+                let mut buf = [0_u8; 64];
+                let _ = stream.read(&mut buf).unwrap();
+                stream.write_all(b"+PONG\r\n").unwrap();
+            }
+        });
+        port
+    }
+
+    /// Serves a single `+PONG` reply over TLS to a client presenting a
+    /// certificate signed by the test CA, sending the common name of that
+    /// certificate, or `None` when the handshake failed.
+    fn mutual_tls_server() -> (u16, mpsc::Receiver<Option<String>>) {
+        let mut builder = SslAcceptor::mozilla_intermediate_v5(SslMethod::tls()).unwrap();
+        builder
+            .set_private_key_file(fixture("server.key"), SslFiletype::PEM)
+            .unwrap();
+        builder
+            .set_certificate_chain_file(fixture("server.crt"))
+            .unwrap();
+        builder.set_ca_file(fixture("ca.crt")).unwrap();
+        builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        let acceptor = builder.build();
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut stream = match acceptor.accept(stream) {
+                Ok(stream) => stream,
+                Err(_) => return sender.send(None).unwrap(),
+            };
+            let name = stream.ssl().peer_certificate().and_then(|cert| {
+                let entry = cert.subject_name().entries_by_nid(Nid::COMMONNAME).next()?;
+                String::from_utf8(entry.data().as_slice().to_vec()).ok()
+            });
+            sender.send(name).unwrap();
+            let mut buf = [0_u8; 64];
+            let _ = stream.read(&mut buf).unwrap();
+            stream.write_all(b"+PONG\r\n").unwrap();
+        });
+        (port, receiver)
+    }
+
+    fn options(port: u16, tls: TlsOptions) -> ConnectionOptions {
+        ConnectionOptions {
+            tls: Some(tls),
+            ..ConnectionOptions::new("127.0.0.1", port)
+        }
+    }
+
+    #[test]
+    fn test_tls_with_ca_and_sni() {
+        let port = tls_server();
+        let transport = Transport::connect(&options(
+            port,
+            TlsOptions {
+                ca_cert: Some(fixture("ca.crt")),
+                cert: Some(fixture("client.crt")),
+                key: Some(fixture("client.key")),
+                sni: Some(String::from("localhost")),
+                ..TlsOptions::default()
+            },
+        ))
+        .unwrap();
+
+        let connection = Connection::new(transport);
+        assert_eq!(connection.request(&["PING"]).unwrap(), Data::from("PONG"));
+    }
+
+    #[test]
+    fn test_tls_client_certificate() {
+        let (port, names) = mutual_tls_server();
+        let transport = Transport::connect(&options(
+            port,
+            TlsOptions {
+                ca_cert: Some(fixture("ca.crt")),
+                cert: Some(fixture("client.crt")),
+                key: Some(fixture("client.key")),
+                sni: Some(String::from("localhost")),
+                ..TlsOptions::default()
+            },
+        ))
+        .unwrap();
+
+        let connection = Connection::new(transport);
+        assert_eq!(connection.request(&["PING"]).unwrap(), Data::from("PONG"));
+        assert_eq!(names.recv().unwrap().as_deref(), Some("redash client"));
+    }
+
+    #[test]
+    fn test_tls_without_required_client_certificate() {
+        let (port, names) = mutual_tls_server();
+        // with TLS 1.3 the server only rejects the handshake after the client
+        // considers it done, so the failure may surface on the first request
+        let result = Transport::connect(&options(
+            port,
+            TlsOptions {
+                ca_cert: Some(fixture("ca.crt")),
+                sni: Some(String::from("localhost")),
+                ..TlsOptions::default()
+            },
+        ))
+        .and_then(|transport| Connection::new(transport).request(&["PING"]));
+
+        assert!(result.is_err());
+        assert_eq!(names.recv().unwrap(), None);
+    }
+
+    #[test]
+    fn test_tls_rejects_unknown_ca() {
+        let port = tls_server();
+        let result = Transport::connect(&options(port, TlsOptions::default()));
+        assert!(matches!(result, Err(RedashError::UnknownError(_))));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_unix_socket() {
+        use std::os::unix::net::UnixListener;
+
+        let path = std::env::temp_dir().join(format!("redash-test-{}.sock", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let listener = UnixListener::bind(&path).unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0_u8; 64];
+            let _ = stream.read(&mut buf).unwrap();
+            stream.write_all(b"+PONG\r\n").unwrap();
+        });
+
+        let transport = Transport::connect(&ConnectionOptions::unix(&path)).unwrap();
+        let connection = Connection::new(transport);
+        assert_eq!(connection.request(&["PING"]).unwrap(), Data::from("PONG"));
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_tls_insecure_skips_verification() {
+        let port = tls_server();
+        let transport = Transport::connect(&options(
+            port,
+            TlsOptions {
+                insecure: true,
+                ..TlsOptions::default()
+            },
+        ))
+        .unwrap();
+
+        let connection = Connection::new(transport);
+        assert_eq!(connection.request(&["PING"]).unwrap(), Data::from("PONG"));
+    }
+}
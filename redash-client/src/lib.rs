@@ -1,4 +1,6 @@
+pub mod args;
 pub mod client;
 pub mod errors;
 pub use client::errors::RedashError;
+pub use client::from_data::FromData;
 pub use client::parser::Data;
@@ -1,10 +1,8 @@
-use std::error::Error;
+use std::{error::Error, process};
 
 use clap::{Parser, Subcommand};
 
-use crate::client::Client;
-mod client;
-mod errors;
+use redash_client::{args::ConnectionArgs, client::Client};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -12,11 +10,8 @@ struct Cli {
     /// Optional name to operate on
     name: Option<String>,
 
-    #[arg(long, value_name = "HOST")]
-    host: Option<String>,
-
-    #[arg(long, value_name = "PORT")]
-    port: Option<u16>,
+    #[command(flatten)]
+    connection: ConnectionArgs,
 
     #[command(subcommand)]
     command: Option<Commands>,
@@ -32,16 +27,14 @@ enum Commands {
     },
 }
 
-static DEFAULT_PORT: u16 = 6379;
-static DEFAULT_HOST: &str = "127.0.0.1";
-
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
-    let port = &cli.port.unwrap_or_else(|| DEFAULT_PORT);
-    let host = &cli.host.unwrap_or_else(|| String::from(DEFAULT_HOST));
 
-    let mut client = Client::new(host, *port);
-    client.connect().unwrap();
+    let client = Client::with_options(cli.connection.options()?);
+    if let Err(err) = client.connect() {
+        eprintln!("could not connect: {err}");
+        process::exit(1);
+    }
     let res = (client).send_command("PING")?;
     println!("{:?}", res);
     Ok(())
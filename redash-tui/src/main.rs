@@ -1,9 +1,9 @@
-use std::{env, error::Error};
+use std::{env, error::Error, process};
 
 use clap::Parser;
 use constants::FOCUS_COLOR;
 use pancurses::{endwin, init_pair, initscr, noecho, raw, start_color, COLOR_CYAN};
-use redash_client::client::Client;
+use redash_client::{args::ConnectionArgs, client::Client};
 use tui::run;
 
 pub mod app;
@@ -16,22 +16,18 @@ struct Cli {
     /// Optional name to operate on
     name: Option<String>,
 
-    #[arg(long, value_name = "HOST")]
-    host: Option<String>,
-
-    #[arg(long, value_name = "PORT")]
-    port: Option<u16>,
+    #[command(flatten)]
+    connection: ConnectionArgs,
 }
 
-static DEFAULT_PORT: u16 = 6379;
-static DEFAULT_HOST: &str = "127.0.0.1";
 fn main() -> Result<(), Box<dyn Error>> {
     let cli = Cli::parse();
-    let port = &cli.port.unwrap_or_else(|| DEFAULT_PORT);
-    let host = &cli.host.unwrap_or_else(|| String::from(DEFAULT_HOST));
 
-    let mut client = Client::new(host, *port);
-    client.connect().unwrap();
+    let client = Client::with_options(cli.connection.options()?);
+    if let Err(err) = client.connect() {
+        eprintln!("could not connect: {err}");
+        process::exit(1);
+    }
     let current_esc_delay = match env::var("ESCDELAY") {
         Ok(v) => v,
         Err(_) => String::from("0"),
@@ -1,8 +1,8 @@
-use std::{cell::RefCell, error::Error, rc::Rc};
+use std::{cell::RefCell, error::Error, rc::Rc, sync::mpsc::Receiver};
 extern crate pancurses;
 
 use pancurses::{Input, Window};
-use redash_client::client::Client;
+use redash_client::client::{reconnect::ReconnectEvent, Client};
 
 use redash_client::Data;
 
@@ -42,7 +42,58 @@ impl Position {
 }
 
 fn create_cell<T>(t: T) -> Rc<RefCell<T>> {
-    return Rc::new(RefCell::new(t));
+    Rc::new(RefCell::new(t))
+}
+
+/// Splits a response into the lines shown in the result panel, one per
+/// element for aggregate replies, optionally prefixed with the element index.
+fn response_lines(response: &Data, numbered: bool) -> Vec<String> {
+    let lines: Vec<String> = match response {
+        Data::Array(array) | Data::Set(array) | Data::Push(array) => {
+            array.iter().map(|data| format!("{data}")).collect()
+        }
+        Data::Map(entries) => entries
+            .iter()
+            .map(|(key, value)| format!("{key} => {value}"))
+            .collect(),
+        Data::Attribute { data, .. } => return response_lines(data, numbered),
+        data => return vec![format!("{data}")],
+    };
+
+    if !numbered {
+        return lines;
+    }
+    lines
+        .into_iter()
+        .enumerate()
+        .map(|(idx, line)| format!("{idx}. {line}"))
+        .collect()
+}
+
+/// Sends a command typed in the input box. Returns the lines of the result
+/// panel, led by the reconnections that happened meanwhile, and the
+/// response when the command succeeded.
+fn submit_command(
+    client: &Client,
+    command: &str,
+    reconnect_events: &Receiver<ReconnectEvent>,
+) -> (Vec<String>, Option<Data>) {
+    let response = client.send_command(command);
+
+    let mut lines: Vec<String> = reconnect_events
+        .try_iter()
+        .map(|reconnect_event| format!("-- {reconnect_event}"))
+        .collect();
+    match response {
+        Ok(result) => {
+            lines.extend(response_lines(&result, true));
+            (lines, Some(result))
+        }
+        Err(err) => {
+            lines.push(format!("{err}"));
+            (lines, None)
+        }
+    }
 }
 
 pub struct App<'a> {
@@ -56,14 +107,14 @@ pub struct App<'a> {
 
 impl<'a> App<'a> {
     pub fn new(window: &'a Window, redis_client: &'a Client) -> Self {
-        return App {
+        App {
             window,
             history_list: Rc::new(RefCell::new(Vec::new())),
             redis_client,
             renderer: Renderer::new(window),
             components: Vec::new(),
             current_focus: 0,
-        };
+        }
     }
 
     pub fn init(&mut self) -> Result<(), Box<dyn Error>> {
@@ -102,48 +153,34 @@ impl<'a> App<'a> {
         let command_list_cloned = command_list.clone();
         let result_list_cloned = result_list.clone();
         let history_list = self.history_list.clone();
-        let input_submit_handler = move |event| match (command_list_cloned).try_borrow_mut() {
-            Ok(mut a) => {
+        let reconnect_events = redis_client.reconnect_events();
+        let input_submit_handler = move |event| {
+            if let Ok(mut a) = command_list_cloned.try_borrow_mut() {
                 if let Event {
                     event_data: EventData::String(command),
                     ..
                 } = event
                 {
-                    match result_list_cloned.try_borrow_mut() {
-                        Ok(mut result_lst) => {
-                            let response = redis_client.send_command(&command);
-
-                            result_lst.clear();
-                            match response {
-                                Ok(result) => {
-                                    {
-                                        match &result {
-                                            Data::Array(array) => {
-                                                for (idx, data) in array.iter().enumerate() {
-                                                    result_lst
-                                                        .append_items(format!("{idx}. {data}"));
-                                                }
-                                            }
-
-                                            data => result_lst.append_items(format!("{data}")),
-                                        }
-                                    }
-                                    let mut history_list = history_list.borrow_mut();
-                                    history_list.push(CommandEntry {
-                                        command: command.clone(),
-                                        response: result,
-                                    });
-                                    a.append_items(command);
-                                    a.next_item();
-                                }
-                                Err(err) => result_lst.append_items(format!("{err}")),
-                            }
+                    if let Ok(mut result_lst) = result_list_cloned.try_borrow_mut() {
+                        let (lines, response) =
+                            submit_command(redis_client, &command, &reconnect_events);
+
+                        result_lst.clear();
+                        for line in lines {
+                            result_lst.append_items(line);
+                        }
+                        if let Some(result) = response {
+                            let mut history_list = history_list.borrow_mut();
+                            history_list.push(CommandEntry {
+                                command: command.clone(),
+                                response: result,
+                            });
+                            a.append_items(command);
+                            a.next_item();
                         }
-                        _ => {}
                     }
                 }
             }
-            Err(_) => (),
         };
 
         if let Ok(mut p) = input_box_cloned.try_borrow_mut() {
@@ -162,22 +199,13 @@ impl<'a> App<'a> {
                 ..
             } = event
             {
-                match result_list_cloned.try_borrow_mut() {
-                    Ok(mut result_lst) => {
-                        result_lst.clear();
-                        let history_list = history_list.borrow();
-                        let entry = history_list.get(value as usize).unwrap();
-                        match &entry.response {
-                            Data::Array(array) => {
-                                for data in array {
-                                    result_lst.append_items(format!("{data}"))
-                                }
-                            }
-
-                            data => result_lst.append_items(format!("{data}")),
-                        }
+                if let Ok(mut result_lst) = result_list_cloned.try_borrow_mut() {
+                    result_lst.clear();
+                    let history_list = history_list.borrow();
+                    let entry = history_list.get(value as usize).unwrap();
+                    for line in response_lines(&entry.response, false) {
+                        result_lst.append_items(line)
                     }
-                    _ => {}
                 }
             }
         };
@@ -233,17 +261,15 @@ impl<'a> App<'a> {
         self.components.iter().for_each(|p| {
             let c = p.0.clone();
 
-            match c.try_borrow() {
-                Ok(c) => c.render(&self.renderer, &p.1),
-                Err(_) => (),
+            if let Ok(c) = c.try_borrow() {
+                c.render(&self.renderer, &p.1)
             };
         });
         self.components.iter().for_each(|p| {
             let c = p.0.clone();
 
-            match c.try_borrow() {
-                Ok(c) => c.render_focus(&self.window, &p.1),
-                Err(_) => (),
+            if let Ok(c) = c.try_borrow() {
+                c.render_focus(self.window, &p.1)
             };
         })
     }
@@ -266,23 +292,19 @@ impl<'a> App<'a> {
         // remove current focus
         let current_focus = self.components[self.current_focus].0.clone();
         // let mut current_focus = current_focus.borrow_mut();
-        match current_focus.try_borrow_mut() {
-            Ok(mut c) => c.toggle_focus(),
-            Err(_) => (),
+        if let Ok(mut c) = current_focus.try_borrow_mut() {
+            c.toggle_focus()
         };
 
         // set next component focus
         self.current_focus = (self.current_focus + 1) % total_component;
         let current_focus = self.components[self.current_focus].0.clone();
-        match current_focus.try_borrow_mut() {
-            Ok(mut c) => {
-                if c.is_focusable() {
-                    c.toggle_focus()
-                } else {
-                    self.focus_next()
-                }
+        if let Ok(mut c) = current_focus.try_borrow_mut() {
+            if c.is_focusable() {
+                c.toggle_focus()
+            } else {
+                self.focus_next()
             }
-            Err(_) => (),
         };
     }
 }
@@ -302,12 +324,52 @@ pub fn run(redis_client: &Client, window: &Window) -> Result<(), Box<dyn Error>>
                 }
             }
             Some(input) => {
-                if let Err(err) = app.handle_input(input) {
-                    return Err(err);
-                }
+                app.handle_input(input)?;
             }
             None => (),
         }
     }
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use redash_client::client::fake_server::FakeServer;
+
+    use super::*;
+
+    #[test]
+    fn test_submit_command() {
+        let server = FakeServer::start().unwrap();
+        let client = server.connect().unwrap();
+        let reconnect_events = client.reconnect_events();
+
+        submit_command(&client, "RPUSH l a b", &reconnect_events);
+        let (lines, response) = submit_command(&client, "LRANGE l 0 -1", &reconnect_events);
+        assert_eq!(lines, ["0. a", "1. b"]);
+        assert_eq!(
+            response,
+            Some(Data::Array(vec![Data::from("a"), Data::from("b")]))
+        );
+
+        let (lines, response) = submit_command(&client, "INCR l", &reconnect_events);
+        assert!(lines[0].starts_with("WRONGTYPE"));
+        assert_eq!(response, None);
+
+        // the broken connection is reported, then the reconnection is shown
+        // before the next response
+        server.disconnect_all();
+        let (_, response) = submit_command(&client, "LLEN l", &reconnect_events);
+        assert_eq!(response, None);
+        let (lines, response) = submit_command(&client, "LLEN l", &reconnect_events);
+        assert_eq!(
+            lines,
+            [
+                "-- reconnecting (attempt 1)",
+                "-- reconnected after 1 attempt(s)",
+                "2"
+            ]
+        );
+        assert_eq!(response, Some(Data::Integer(2)));
+    }
+}
@@ -33,7 +33,7 @@ impl<'a> InputBox<'a> {
         }
     }
     fn is_overflow(&self) -> bool {
-        return self.width - 2 < self.value.len() as i32;
+        self.width - 2 < self.value.len() as i32
     }
     pub fn focus(&mut self) {
         self.is_focus = true;
@@ -112,7 +112,7 @@ impl<'a> Component<'a> for InputBox<'a> {
             &self.value[..]
         };
         renderer.draw_string(
-            &rendering_text,
+            rendering_text,
             TextStyle::Normal,
             false,
             &value_text_render_box,
@@ -169,13 +169,11 @@ impl<'a> Component<'a> for InputBox<'a> {
                     }
                     '\r' => return true,
                     a if !a.is_ascii_control() || a == &'\n' || a == &'\r' => {
-                        if !self.insert_mode {
-                            if *a == 'i' || *a == 'a' {
-                                self.insert_mode = true;
-                                self.input_mode_changed();
+                        if !self.insert_mode && (*a == 'i' || *a == 'a') {
+                            self.insert_mode = true;
+                            self.input_mode_changed();
 
-                                return true;
-                            }
+                            return true;
                         }
                         if self.insert_mode {
                             self.value = format!("{}{a}", self.value);
@@ -200,7 +198,7 @@ impl<'a> Component<'a> for InputBox<'a> {
         {
             self.is_focus = true;
         }
-        return false;
+        false
     }
 
     fn toggle_focus(&mut self) {
@@ -212,7 +210,7 @@ impl<'a> Component<'a> for InputBox<'a> {
         event_type: EventType,
         handler: Rc<RefCell<EventHandler<'a>>>,
     ) {
-        let event_handlers = self.event_listeners.entry(event_type).or_insert(Vec::new());
+        let event_handlers = self.event_listeners.entry(event_type).or_default();
         event_handlers.push(handler);
     }
 
@@ -221,6 +219,6 @@ impl<'a> Component<'a> for InputBox<'a> {
     }
 
     fn is_focusable(&self) -> bool {
-        return true;
+        true
     }
 }
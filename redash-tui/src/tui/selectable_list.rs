@@ -153,7 +153,7 @@ impl<'a, T: Display> Component<'a> for SelectableList<'a, T> {
                     width: 0,
                     height: (self.height - 3) as usize,
                 },
-                ((self.viewport_top) as f64) / (((len as i32) - self.height + 2) as f64),
+                ((self.viewport_top) as f64) / ((len - self.height + 2) as f64),
             )
         }
         for (index, item) in rendering_items.iter().enumerate() {
@@ -218,7 +218,7 @@ impl<'a, T: Display> Component<'a> for SelectableList<'a, T> {
             return true;
         }
 
-        return false;
+        false
     }
 
     fn add_event_listener(
@@ -226,7 +226,7 @@ impl<'a, T: Display> Component<'a> for SelectableList<'a, T> {
         event_type: EventType,
         handler: Rc<RefCell<EventHandler<'a>>>,
     ) {
-        let event_handlers = self.event_listeners.entry(event_type).or_insert(Vec::new());
+        let event_handlers = self.event_listeners.entry(event_type).or_default();
         event_handlers.push(handler);
     }
 
@@ -239,6 +239,6 @@ impl<'a, T: Display> Component<'a> for SelectableList<'a, T> {
     }
 
     fn is_focusable(&self) -> bool {
-        return true;
+        true
     }
 }
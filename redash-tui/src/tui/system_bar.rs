@@ -44,7 +44,7 @@ impl<'a> Component<'a> for SystemBar {
     fn render_focus(&self, _: &Window, _: &Position) {}
 
     fn trigger(&mut self, _: &Event) -> bool {
-        return false;
+        false
     }
 
     fn add_event_listener(&mut self, _: EventType, _: Rc<RefCell<EventHandler<'a>>>) {}
@@ -56,6 +56,6 @@ impl<'a> Component<'a> for SystemBar {
     }
 
     fn is_focusable(&self) -> bool {
-        return false;
+        false
     }
 }